@@ -23,6 +23,6 @@ fn main() {
     }
 
     // Wait until all 100 threads are finished
-    wg.wait();
+    wg.wait().unwrap();
     println!("{}", counter.load(Ordering::SeqCst)); //100
 }
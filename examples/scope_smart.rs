@@ -22,6 +22,6 @@ fn main() {
     }
 
     // Wait until all 100 threads are finished
-    wg.waiter().wait();
+    wg.waiter().wait().unwrap();
     println!("{}", counter.load(Ordering::SeqCst)); //100
 }
@@ -0,0 +1,22 @@
+use std::sync::atomic::{AtomicIsize, Ordering};
+
+use wait_group::SmartWaitGroup;
+
+fn main() {
+    let counter = AtomicIsize::new(0);
+
+    let wg = SmartWaitGroup::new();
+
+    // Spawn 100 threads borrowing the counter, each of them holds a doer
+    wg.scope(|s| {
+        for _ in 0..100 {
+            s.spawn(|| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+    });
+
+    // Spawned threads are joined, so the group is already drained
+    wg.waiter().wait().unwrap();
+    println!("{}", counter.load(Ordering::SeqCst)); //100
+}
@@ -26,6 +26,6 @@ fn main() {
     spawn_threads(Arc::clone(&counter), doer);
 
     // Wait until all 100 threads are finished
-    waiter.wait();
+    waiter.wait().unwrap();
     println!("{}", counter.load(Ordering::SeqCst)); //100
 }
@@ -0,0 +1,60 @@
+use wait_group::{Case, MultiDoer, MultiWaitGroup};
+
+use rayon::ThreadPoolBuilder;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[derive(Clone, Copy, Case)]
+enum Task {
+    Normal,
+    Special,
+}
+
+struct Context {
+    resource_counter: AtomicUsize,
+    wg: MultiWaitGroup<Task>, //WaitGroups for normal and special tasks
+}
+impl Context {
+    fn new() -> Self {
+        Context {
+            resource_counter: AtomicUsize::new(0),
+            wg: MultiWaitGroup::new(),
+        }
+    }
+}
+
+fn normal_task(c: Arc<Context>, _normal_doer: MultiDoer<Task>) {
+    c.resource_counter.fetch_add(1, Ordering::SeqCst);
+    //drop(_normal_doer) implicit call
+}
+
+fn special_task(c: Arc<Context>, _special_doer: MultiDoer<Task>) {
+    c.resource_counter.store(0, Ordering::SeqCst);
+    //drop(_special_doer) implicit call
+}
+
+fn task(c: Arc<Context>) {
+    let normal_doer = c.wg.switch_wait_do(Task::Normal, Task::Special);
+    normal_task(Arc::clone(&c), normal_doer);
+
+    if c.resource_counter.load(Ordering::SeqCst) >= 60 {
+        if let Some(special_doer) = c.wg.switch_unique(Task::Special, Task::Normal) {
+            special_task(Arc::clone(&c), special_doer);
+        }
+    }
+}
+
+fn main() {
+    let pool = ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+
+    let context = Arc::new(Context::new());
+
+    pool.scope(|s| {
+        for _ in 0..100 {
+            let context = context.clone();
+            s.spawn(|_| task(context));
+        }
+    });
+
+    println!("{}", context.resource_counter.load(Ordering::SeqCst));
+}
@@ -31,13 +31,13 @@ fn special_task(c: Arc<Context>, _special_doer: Doer) {
 }
 
 fn task(c: Arc<Context>) {
-    c.special_wg.waiter().wait();
+    c.special_wg.waiter().wait().unwrap();
     let normal_doer = c.normal_wg.doer();
     normal_task(Arc::clone(&c), normal_doer);
 
     if c.resource_counter.load(Ordering::SeqCst) >= 60 {
         if let Some(special_doer) = c.special_wg.unique_doer() {
-            c.normal_wg.waiter().wait();
+            c.normal_wg.waiter().wait().unwrap();
             special_task(Arc::clone(&c), special_doer);
         }
     }
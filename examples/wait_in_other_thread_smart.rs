@@ -16,7 +16,7 @@ fn heavy_process_counter(counter: Arc<AtomicIsize>, wg: Waiter) {
         sleep(Duration::from_secs(1)); //emulation of some heavy preparing
         42
     };
-    wg.wait(); //deadlock
+    wg.wait().unwrap(); //deadlock
     eprintln!("{}", counter.load(Ordering::SeqCst) + delta); //142
 }
 
@@ -38,6 +38,6 @@ fn main() {
     let (waiter, doer) = SmartWaitGroup::splitted();
     spawn_process_threads(Arc::clone(&counter), doer);
     spawn_heavy_process_thread(Arc::clone(&counter), waiter.clone());
-    waiter.wait();
+    waiter.wait().unwrap();
     println!("{}", counter.load(Ordering::SeqCst)); //100
 }
\ No newline at end of file
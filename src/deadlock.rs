@@ -0,0 +1,105 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use std::thread::{self, ThreadId};
+
+use crate::lock::Mutex;
+
+// Wait-for graph of all groups which detect deadlocks.
+// Groups are identified by the address of their WaitGroupImpl, which outlives
+// every edge: holders and waiters keep the group alive.
+struct Graph {
+    // Thread which switched to a doer of the group, one entry per doer
+    holders: Vec<(usize, ThreadId)>,
+    waiting: Vec<Waiting>,
+}
+
+struct Waiting {
+    thread: ThreadId,
+    group: usize,
+    name: Option<Arc<str>>,
+}
+
+static GRAPH: Mutex<Graph> = Mutex::new(Graph {
+    holders: Vec::new(),
+    waiting: Vec::new(),
+});
+
+impl Graph {
+    // Whether `thread` is reachable from the holders of `group`, i.e. waiting for
+    // the group closes a cycle. Path collects waiting entries of the cycle.
+    fn search(
+        &self,
+        thread: ThreadId,
+        group: usize,
+        visited: &mut Vec<usize>,
+        path: &mut Vec<usize>,
+    ) -> bool {
+        if visited.contains(&group) {
+            return false;
+        }
+        visited.push(group);
+        for &(_, holder) in self.holders.iter().filter(|(held, _)| *held == group) {
+            if holder == thread {
+                return true;
+            }
+            if let Some(index) = self.waiting.iter().position(|w| w.thread == holder) {
+                path.push(index);
+                if self.search(thread, self.waiting[index].group, visited, path) {
+                    return true;
+                }
+                path.pop();
+            }
+        }
+        false
+    }
+}
+
+pub fn hold(group: usize) -> ThreadId {
+    let thread = thread::current().id();
+    GRAPH.lock().holders.push((group, thread));
+    thread
+}
+
+pub fn release(group: usize, thread: ThreadId) {
+    let mut graph = GRAPH.lock();
+    if let Some(index) = graph
+        .holders
+        .iter()
+        .position(|&(held, holder)| held == group && holder == thread)
+    {
+        graph.holders.swap_remove(index);
+    }
+}
+
+// Edge from the current thread to the group it waits for, removed on drop
+pub struct WaitEdge {
+    thread: ThreadId,
+}
+
+impl Drop for WaitEdge {
+    fn drop(&mut self) {
+        let mut graph = GRAPH.lock();
+        if let Some(index) = graph.waiting.iter().position(|w| w.thread == self.thread) {
+            graph.waiting.swap_remove(index);
+        }
+    }
+}
+
+// On deadlock returns names of the groups in the cycle, starting from the waited one
+pub fn wait_for(group: usize, name: Option<Arc<str>>) -> Result<WaitEdge, Vec<Option<Arc<str>>>> {
+    let thread = thread::current().id();
+    let mut graph = GRAPH.lock();
+    let mut path = Vec::new();
+    if graph.search(thread, group, &mut Vec::new(), &mut path) {
+        let mut cycle = Vec::with_capacity(path.len() + 1);
+        cycle.push(name);
+        cycle.extend(path.iter().map(|&index| graph.waiting[index].name.clone()));
+        return Err(cycle);
+    }
+    graph.waiting.push(Waiting {
+        thread,
+        group,
+        name,
+    });
+    Ok(WaitEdge { thread })
+}
@@ -0,0 +1,45 @@
+use alloc::boxed::Box;
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use core::time::Duration;
+
+use crate::wait_strategy::WaitStrategy;
+
+// Word-sized event counter which threads can sleep on until it changes.
+// How threads sleep is up to the wait strategy.
+pub struct Event {
+    word: AtomicU32,
+    // Number of threads inside `wait`, so waking is a no-op
+    // when nobody sleeps
+    sleepers: AtomicUsize,
+    strategy: Box<dyn WaitStrategy>,
+}
+
+impl Event {
+    pub fn new(strategy: Box<dyn WaitStrategy>) -> Event {
+        Event {
+            word: AtomicU32::new(0),
+            sleepers: AtomicUsize::new(0),
+            strategy,
+        }
+    }
+
+    pub fn load(&self) -> u32 {
+        self.word.load(Ordering::SeqCst)
+    }
+
+    // Change the word and wake up every thread sleeping on it
+    pub fn bump(&self) {
+        self.word.fetch_add(1, Ordering::SeqCst);
+        if self.sleepers.load(Ordering::SeqCst) > 0 {
+            self.strategy.wake_all(&self.word);
+        }
+    }
+
+    // Block while the word is equal to `expected`, but no longer than `timeout`.
+    // May return spuriously.
+    pub fn wait(&self, expected: u32, timeout: Option<Duration>) {
+        self.sleepers.fetch_add(1, Ordering::SeqCst);
+        self.strategy.wait(&self.word, expected, timeout);
+        self.sleepers.fetch_sub(1, Ordering::SeqCst);
+    }
+}
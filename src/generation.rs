@@ -0,0 +1,16 @@
+// Number of times the counter of a wait group has dropped to zero.
+// Waiters blocked at that moment are released for that generation,
+// even if the counter was raised again before they woke up.
+// Wraps around to zero after u32::MAX, or after 4095 on targets without 64-bit atomics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Generation(usize);
+
+impl Generation {
+    pub(crate) fn new(value: usize) -> Self {
+        Generation(value)
+    }
+
+    pub fn value(self) -> usize {
+        self.0
+    }
+}
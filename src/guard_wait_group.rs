@@ -1,64 +1,249 @@
-use std::fmt;
-use std::sync::Arc;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use core::time::Duration;
 
+use crate::generation::Generation;
+#[cfg(feature = "metrics")]
+use crate::metrics::Stats;
+use crate::subscription::Subscription;
+use crate::sys;
+#[cfg(feature = "std")]
+use crate::sys::Instant;
 use crate::wait_group_impl::WaitGroupImpl;
+use crate::{DoerInfo, Result, WaitStrategy};
 
 pub struct GuardWaitGroup {
     inner: Arc<WaitGroupImpl>,
+    #[cfg(feature = "metrics")]
+    started: Instant,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    // Id in the registry of the group, clones are registered only
+    record: Option<usize>,
+    // Kept by the guard itself, so an unregistered one still passes it to clones
+    label: Option<Arc<str>>,
+    // The root isn't counted, unlike clones, so it's never released
+    root: bool,
 }
 
 impl GuardWaitGroup {
     pub fn new() -> GuardWaitGroup {
-        GuardWaitGroup {
-            inner: Arc::new(WaitGroupImpl::new()),
-        }
+        Self::from_inner(Arc::new(WaitGroupImpl::new()), true)
+    }
+
+    pub fn with_strategy<S: WaitStrategy + 'static>(strategy: S) -> GuardWaitGroup {
+        Self::from_inner(
+            Arc::new(WaitGroupImpl::with_strategy(Box::new(strategy))),
+            true,
+        )
+    }
+
+    pub fn named<N: Into<Arc<str>>>(self, name: N) -> Self {
+        self.inner.set_name(name.into());
+        self
+    }
+
+    pub fn name(&self) -> Option<Arc<str>> {
+        self.inner.name()
+    }
+
+    pub fn wait(&self) -> Result<Generation> {
+        self.inner.wait()
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_timeout(&self, timeout: Duration) -> Result<Generation> {
+        self.inner.wait_timeout(timeout)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_deadline(&self, deadline: Instant) -> Result<Generation> {
+        self.inner.wait_deadline(deadline)
+    }
+
+    pub fn try_wait(&self) -> bool {
+        self.inner.try_wait()
+    }
+
+    pub fn wait_until_at_most(&self, threshold: usize) -> Result<usize> {
+        self.inner.wait_at_most_until(threshold, None)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_until_at_most_timeout(&self, threshold: usize, timeout: Duration) -> Result<usize> {
+        self.inner
+            .wait_at_most_until(threshold, sys::deadline_after(timeout))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_until_at_most_deadline(
+        &self,
+        threshold: usize,
+        deadline: Instant,
+    ) -> Result<usize> {
+        self.inner.wait_at_most_until(threshold, Some(deadline))
+    }
+
+    pub fn is_poisoned(&self) -> bool {
+        self.inner.is_poisoned()
+    }
+
+    pub fn clear_poison(&self) {
+        self.inner.clear_poison();
+    }
+
+    pub fn on_complete<F>(&self, callback: F)
+    where
+        F: FnOnce(Result<Generation>) + Send + 'static,
+    {
+        self.inner.on_complete(Box::new(callback));
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancel();
     }
 
-    pub fn wait(&self) {
-        self.inner.wait();
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.is_cancelled()
+    }
+
+    pub fn check_cancelled(&self) -> Result<()> {
+        self.inner.check_cancelled()
+    }
+
+    pub fn close(&self) {
+        self.inner.close();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+
+    pub fn wait_cancelled(&self) {
+        self.inner.wait_cancelled_until(None);
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_cancelled_timeout(&self, timeout: Duration) -> bool {
+        self.inner
+            .wait_cancelled_until(sys::deadline_after(timeout))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_cancelled_deadline(&self, deadline: Instant) -> bool {
+        self.inner.wait_cancelled_until(Some(deadline))
+    }
+
+    pub fn subscribe(&self) -> Subscription {
+        Subscription::new(Arc::clone(&self.inner))
     }
 
     pub fn counter(&self) -> usize {
         self.inner.counter()
     }
 
+    pub fn generation(&self) -> Generation {
+        self.inner.generation()
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn stats(&self) -> Stats {
+        self.inner.stats()
+    }
+
     pub fn clone_unique(&self) -> Option<Self> {
         if self.inner.increment_if_empty() {
-            Some(GuardWaitGroup {
-                inner: Arc::clone(&self.inner),
-            })
+            let mut wg = Self::from_inner(Arc::clone(&self.inner), false);
+            wg.record = self.inner.registry().register(self.label.clone());
+            wg.label = self.label.clone();
+            #[cfg(feature = "tracing")]
+            wg.inner.trace_added(&wg.span);
+            Some(wg)
         } else {
             None
         }
     }
 
+    // Doers created afterwards are registered with their creating thread
+    // and, if requested, backtrace, until they are released
+    pub fn track_doers(self, backtraces: bool) -> Self {
+        self.inner.track_doers(backtraces);
+        self
+    }
+
+    pub fn outstanding(&self) -> Vec<DoerInfo> {
+        self.inner.outstanding()
+    }
+
+    // Label is reported among the outstanding doers and inherited by clones
+    pub fn labeled<L: Into<Arc<str>>>(mut self, label: L) -> Self {
+        let label = label.into();
+        if let Some(id) = self.record {
+            self.inner.registry().set_label(id, Arc::clone(&label));
+        }
+        self.label = Some(label);
+        self
+    }
+
+    fn from_inner(inner: Arc<WaitGroupImpl>, root: bool) -> GuardWaitGroup {
+        GuardWaitGroup {
+            #[cfg(feature = "tracing")]
+            span: inner.doer_span(),
+            inner,
+            #[cfg(feature = "metrics")]
+            started: Instant::now(),
+            record: None,
+            label: None,
+            root,
+        }
+    }
+
     fn increment_counter(&self) {
-        self.inner.increment();
+        self.inner.increment_admitted();
     }
 
     fn done(&self) {
         self.inner.done();
     }
+}
 
-    unsafe fn inner(&self) -> Arc<WaitGroupImpl> {
-        Arc::clone(&self.inner)
+impl Default for GuardWaitGroup {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl Clone for GuardWaitGroup {
     fn clone(&self) -> Self {
-        let wg = GuardWaitGroup {
-            inner: Arc::clone(&self.inner),
-        };
+        let mut wg = Self::from_inner(Arc::clone(&self.inner), false);
         wg.increment_counter();
+        wg.record = self.inner.registry().register(self.label.clone());
+        wg.label = self.label.clone();
+        #[cfg(feature = "tracing")]
+        wg.inner.trace_added(&wg.span);
         wg
     }
 }
 
 impl Drop for GuardWaitGroup {
     fn drop(&mut self) {
-        if let None = Arc::get_mut(&mut self.inner) {
-            self.done();
+        if !self.root {
+            if let Some(id) = self.record {
+                self.inner.registry().remove(id);
+            }
+            #[cfg(feature = "metrics")]
+            self.inner.record_doer(self.started);
+            let panicking = sys::panicking();
+            if panicking {
+                self.inner.done_panicked();
+            } else {
+                self.done();
+            }
+            #[cfg(feature = "tracing")]
+            self.inner.trace_released(&self.span, panicking);
         }
     }
 }
@@ -0,0 +1,37 @@
+use alloc::vec::Vec;
+use std::thread::{self, ThreadId};
+
+use crate::lock::Mutex;
+
+// Levels of the doers held by threads after switching, one entry per doer.
+// A thread may only wait for a group of a lower level than every doer it holds,
+// so waits are acquired in a strictly decreasing order and can't form a cycle.
+static HELD: Mutex<Vec<(ThreadId, usize)>> = Mutex::new(Vec::new());
+
+pub fn hold(level: usize) -> ThreadId {
+    let thread = thread::current().id();
+    HELD.lock().push((thread, level));
+    thread
+}
+
+pub fn release(thread: ThreadId, level: usize) {
+    let mut held = HELD.lock();
+    if let Some(index) = held.iter().position(|&entry| entry == (thread, level)) {
+        held.swap_remove(index);
+    }
+}
+
+// Returns the lowest held level, if waiting for `level` goes against the hierarchy
+pub fn check(level: usize) -> Result<(), usize> {
+    let thread = thread::current().id();
+    let held = HELD.lock();
+    match held
+        .iter()
+        .filter(|&&(holder, _)| holder == thread)
+        .map(|&(_, held)| held)
+        .min()
+    {
+        Some(held) if held <= level => Err(held),
+        _ => Ok(()),
+    }
+}
@@ -1,10 +1,55 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub use generation::Generation;
 pub use guard_wait_group::GuardWaitGroup;
 pub use manual_wait_group::ManualWaitGroup;
+#[cfg(feature = "metrics")]
+pub use metrics::{Latency, Stats};
+pub use multi_wait_group::{Case, MultiDoer, MultiWaitGroup, MultiWaiter};
+pub use registry::DoerInfo;
 pub use smart_wait_group::{Doer, Order, SmartWaitGroup, Waiter};
-pub use wait_group_error::{Result, WaitGroupError};
+#[cfg(feature = "std")]
+pub use spawn::Scope;
+pub use subscription::Subscription;
+pub use tagged_wait_group::{TaggedDoer, TaggedWaitGroup, TaggedWaiter};
+#[cfg(feature = "rayon")]
+pub use thread_pool::{DoerParallelIterator, WithDoers};
+pub use wait_future::WaitFuture;
+#[cfg(feature = "derive")]
+pub use wait_group_derive::Case;
+pub use wait_group_error::{DoerError, Result, WaitGroupError};
+pub use wait_group_impl::MAX_COUNTER;
+#[cfg(target_os = "linux")]
+pub use wait_strategy::FutexStrategy;
+#[cfg(feature = "std")]
+pub use wait_strategy::{CondvarStrategy, ParkStrategy, SpinThenYieldStrategy};
+pub use wait_strategy::{SpinStrategy, WaitStrategy};
 
+#[cfg(feature = "std")]
+mod deadlock;
+mod event;
+mod generation;
 mod guard_wait_group;
+#[cfg(feature = "std")]
+mod hierarchy;
+mod lock;
 mod manual_wait_group;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod multi_wait_group;
+mod registry;
 mod smart_wait_group;
+#[cfg(feature = "std")]
+mod spawn;
+mod subscription;
+mod sys;
+mod tagged_wait_group;
+#[cfg(feature = "rayon")]
+mod thread_pool;
+mod wait_future;
 mod wait_group_error;
 mod wait_group_impl;
+mod wait_strategy;
+mod waker_list;
@@ -1,6 +1,16 @@
+use crate::generation::Generation;
+#[cfg(feature = "metrics")]
+use crate::metrics::Stats;
+use crate::subscription::Subscription;
+#[cfg(feature = "std")]
+use crate::sys::{self, Instant};
+use crate::wait_future::WaitFuture;
 use crate::wait_group_impl::WaitGroupImpl;
-use crate::Result;
-use std::sync::Arc;
+use crate::{Result, WaitStrategy};
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+#[cfg(feature = "std")]
+use core::time::Duration;
 
 #[derive(Clone)]
 pub struct ManualWaitGroup {
@@ -14,17 +24,125 @@ impl ManualWaitGroup {
         }
     }
 
-    pub fn wait(&self) {
-        self.inner.wait();
+    pub fn with_strategy<S: WaitStrategy + 'static>(strategy: S) -> Self {
+        ManualWaitGroup {
+            inner: Arc::new(WaitGroupImpl::with_strategy(Box::new(strategy))),
+        }
+    }
+
+    pub fn named<N: Into<Arc<str>>>(self, name: N) -> Self {
+        self.inner.set_name(name.into());
+        self
+    }
+
+    pub fn name(&self) -> Option<Arc<str>> {
+        self.inner.name()
+    }
+
+    pub fn wait(&self) -> Result<Generation> {
+        self.inner.wait()
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_timeout(&self, timeout: Duration) -> Result<Generation> {
+        self.inner.wait_timeout(timeout)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_deadline(&self, deadline: Instant) -> Result<Generation> {
+        self.inner.wait_deadline(deadline)
+    }
+
+    pub fn try_wait(&self) -> bool {
+        self.inner.try_wait()
+    }
+
+    pub fn wait_until_at_most(&self, threshold: usize) -> Result<usize> {
+        self.inner.wait_at_most_until(threshold, None)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_until_at_most_timeout(&self, threshold: usize, timeout: Duration) -> Result<usize> {
+        self.inner
+            .wait_at_most_until(threshold, sys::deadline_after(timeout))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_until_at_most_deadline(
+        &self,
+        threshold: usize,
+        deadline: Instant,
+    ) -> Result<usize> {
+        self.inner.wait_at_most_until(threshold, Some(deadline))
+    }
+
+    pub fn is_poisoned(&self) -> bool {
+        self.inner.is_poisoned()
+    }
+
+    pub fn clear_poison(&self) {
+        self.inner.clear_poison();
+    }
+
+    pub fn on_complete<F>(&self, callback: F)
+    where
+        F: FnOnce(Result<Generation>) + Send + 'static,
+    {
+        self.inner.on_complete(Box::new(callback));
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.is_cancelled()
+    }
+
+    pub fn check_cancelled(&self) -> Result<()> {
+        self.inner.check_cancelled()
+    }
+
+    pub fn close(&self) {
+        self.inner.close();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+
+    pub fn wait_cancelled(&self) {
+        self.inner.wait_cancelled_until(None);
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_cancelled_timeout(&self, timeout: Duration) -> bool {
+        self.inner
+            .wait_cancelled_until(sys::deadline_after(timeout))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_cancelled_deadline(&self, deadline: Instant) -> bool {
+        self.inner.wait_cancelled_until(Some(deadline))
+    }
+
+    pub fn wait_async(&self) -> WaitFuture {
+        WaitFuture::new(Arc::clone(&self.inner))
+    }
+
+    pub fn subscribe(&self) -> Subscription {
+        Subscription::new(Arc::clone(&self.inner))
     }
 
-    #[must_use]
     pub fn try_add(&self, delta: isize) -> Result<()> {
-        self.inner.try_add(delta)
+        let res = self.inner.try_add(delta);
+        #[cfg(feature = "tracing")]
+        self.inner.trace_add(delta, &res);
+        res
     }
 
     pub fn add(&self, delta: isize) {
-        self.inner.add(delta);
+        self.try_add(delta).unwrap();
     }
 
     #[must_use]
@@ -32,20 +150,34 @@ impl ManualWaitGroup {
         self.inner.add_if_empty(delta)
     }
 
-    #[must_use]
+    pub fn try_add_if_empty(&self, delta: usize) -> Result<bool> {
+        self.inner.try_add_if_empty(delta)
+    }
+
     pub fn try_done(&self) -> Result<()> {
-        self.inner.try_done()
+        self.try_add(-1)
     }
 
     pub fn done(&self) {
-        self.inner.done();
+        self.try_done().unwrap();
     }
 
     pub fn counter(&self) -> usize {
         self.inner.counter()
     }
 
-    unsafe fn inner(&self) -> Arc<WaitGroupImpl> {
-        Arc::clone(&self.inner)
+    pub fn generation(&self) -> Generation {
+        self.inner.generation()
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn stats(&self) -> Stats {
+        self.inner.stats()
+    }
+}
+
+impl Default for ManualWaitGroup {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -0,0 +1,133 @@
+use core::convert::TryFrom;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use core::time::Duration;
+
+pub const BUCKETS: usize = 32;
+
+// Snapshot of a latency histogram.
+// Bucket i counts samples shorter than 2^i microseconds, the last one counts the rest.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Latency {
+    pub count: u64,
+    pub total: Duration,
+    pub max: Duration,
+    pub buckets: [u64; BUCKETS],
+}
+
+impl Latency {
+    pub fn mean(&self) -> Option<Duration> {
+        match self.count {
+            0 => None,
+            count => Some(Duration::from_nanos(
+                (self.total.as_nanos() / count as u128) as u64,
+            )),
+        }
+    }
+
+    // Upper bound of the bucket, None for the last one
+    pub fn bucket_bound(index: usize) -> Option<Duration> {
+        if index + 1 < BUCKETS {
+            Some(Duration::from_micros(1 << index))
+        } else {
+            None
+        }
+    }
+}
+
+// Snapshot of the group metrics since its creation
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    // Doers ever added to the group
+    pub issued: u64,
+    // Maximal number of simultaneous doers
+    pub peak: usize,
+    pub wait_latency: Latency,
+    pub doer_lifetime: Latency,
+}
+
+struct Histogram {
+    count: AtomicU64,
+    total: AtomicU64,
+    max: AtomicU64,
+    buckets: [AtomicU64; BUCKETS],
+}
+
+impl Histogram {
+    fn new() -> Histogram {
+        Histogram {
+            count: AtomicU64::new(0),
+            total: AtomicU64::new(0),
+            max: AtomicU64::new(0),
+            buckets: Default::default(),
+        }
+    }
+
+    fn record(&self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        let micros = nanos / 1000;
+        let index = if micros == 0 {
+            0
+        } else {
+            (u64::BITS - micros.leading_zeros()) as usize
+        };
+        self.buckets[index.min(BUCKETS - 1)].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total.fetch_add(nanos, Ordering::Relaxed);
+        self.max.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> Latency {
+        let mut buckets = [0; BUCKETS];
+        for (bucket, counter) in buckets.iter_mut().zip(self.buckets.iter()) {
+            *bucket = counter.load(Ordering::Relaxed);
+        }
+        Latency {
+            count: self.count.load(Ordering::Relaxed),
+            total: Duration::from_nanos(self.total.load(Ordering::Relaxed)),
+            max: Duration::from_nanos(self.max.load(Ordering::Relaxed)),
+            buckets,
+        }
+    }
+}
+
+// Metrics are relaxed counters, so a snapshot isn't consistent
+// with the concurrent changes of the group
+pub struct Metrics {
+    issued: AtomicU64,
+    peak: AtomicUsize,
+    wait_latency: Histogram,
+    doer_lifetime: Histogram,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics {
+            issued: AtomicU64::new(0),
+            peak: AtomicUsize::new(0),
+            wait_latency: Histogram::new(),
+            doer_lifetime: Histogram::new(),
+        }
+    }
+
+    pub fn record_added(&self, delta: usize, count: usize) {
+        self.issued.fetch_add(delta as u64, Ordering::Relaxed);
+        self.peak.fetch_max(count, Ordering::Relaxed);
+    }
+
+    pub fn record_wait(&self, duration: Duration) {
+        self.wait_latency.record(duration);
+    }
+
+    pub fn record_doer(&self, duration: Duration) {
+        self.doer_lifetime.record(duration);
+    }
+
+    pub fn stats(&self) -> Stats {
+        Stats {
+            issued: self.issued.load(Ordering::Relaxed),
+            peak: self.peak.load(Ordering::Relaxed),
+            wait_latency: self.wait_latency.snapshot(),
+            doer_lifetime: self.doer_lifetime.snapshot(),
+        }
+    }
+}
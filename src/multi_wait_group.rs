@@ -0,0 +1,209 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use core::time::Duration;
+
+use crate::generation::Generation;
+use crate::sys;
+#[cfg(feature = "std")]
+use crate::sys::Instant;
+use crate::wait_group_impl::WaitGroupImpl;
+use crate::{Order, Result};
+
+// Enum of related tasks, each of them has its own group in MultiWaitGroup.
+// Derive it with `#[derive(Case)]` under the `derive` feature.
+pub trait Case: Copy {
+    const COUNT: usize;
+
+    // Unique index below COUNT
+    fn index(self) -> usize;
+}
+
+// Bundle of groups, one per case, which live in a single allocation
+pub struct MultiWaitGroup<C: Case> {
+    groups: Arc<[WaitGroupImpl]>,
+    case: PhantomData<fn(C)>,
+}
+
+impl<C: Case> MultiWaitGroup<C> {
+    pub fn new() -> Self {
+        let groups = (0..C::COUNT)
+            .map(|_| WaitGroupImpl::new())
+            .collect::<Vec<_>>();
+        MultiWaitGroup {
+            groups: Arc::from(groups),
+            case: PhantomData,
+        }
+    }
+
+    pub fn doer(&self, case: C) -> MultiDoer<C> {
+        MultiDoer::new(Arc::clone(&self.groups), case)
+    }
+
+    pub fn unique_doer(&self, case: C) -> Option<MultiDoer<C>> {
+        MultiDoer::unique(Arc::clone(&self.groups), case)
+    }
+
+    pub fn waiter(&self, case: C) -> MultiWaiter<C> {
+        MultiWaiter {
+            groups: Arc::clone(&self.groups),
+            case,
+        }
+    }
+
+    pub fn counter(&self, case: C) -> usize {
+        self.groups[case.index()].counter()
+    }
+
+    pub fn generation(&self, case: C) -> Generation {
+        self.groups[case.index()].generation()
+    }
+
+    pub fn switch_do_wait(&self, first: C, second: C) -> MultiDoer<C> {
+        // Ensure that first and second are differ for avoiding deadlock
+        assert_ne!(first.index(), second.index());
+
+        let doer = self.doer(first);
+        self.wait_second(second);
+        doer
+    }
+
+    pub fn switch_wait_do(&self, first: C, second: C) -> MultiDoer<C> {
+        // Ensure that first and second are differ for avoiding deadlock
+        assert_ne!(first.index(), second.index());
+
+        self.wait_second(second);
+        self.doer(first)
+    }
+
+    pub fn switch(&self, first: C, second: C, order: Order) -> MultiDoer<C> {
+        match order {
+            Order::DoerWaiter => self.switch_do_wait(first, second),
+            Order::WaiterDoer => self.switch_wait_do(first, second),
+        }
+    }
+
+    pub fn switch_unique(&self, first: C, second: C) -> Option<MultiDoer<C>> {
+        // Ensure that first and second are differ for avoiding deadlock
+        assert_ne!(first.index(), second.index());
+
+        let doer = self.unique_doer(first);
+        if doer.is_some() {
+            self.wait_second(second);
+        }
+        doer
+    }
+
+    // Poison of the second group is reported to its own waiters
+    fn wait_second(&self, second: C) {
+        let _ = self.groups[second.index()].wait();
+    }
+}
+
+impl<C: Case> Default for MultiWaitGroup<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Case> Clone for MultiWaitGroup<C> {
+    fn clone(&self) -> Self {
+        MultiWaitGroup {
+            groups: Arc::clone(&self.groups),
+            case: PhantomData,
+        }
+    }
+}
+
+#[must_use]
+pub struct MultiDoer<C: Case> {
+    groups: Arc<[WaitGroupImpl]>,
+    case: C,
+}
+
+impl<C: Case> MultiDoer<C> {
+    fn new(groups: Arc<[WaitGroupImpl]>, case: C) -> Self {
+        groups[case.index()].increment();
+        MultiDoer { groups, case }
+    }
+
+    fn unique(groups: Arc<[WaitGroupImpl]>, case: C) -> Option<Self> {
+        if groups[case.index()].increment_if_empty() {
+            Some(MultiDoer { groups, case })
+        } else {
+            None
+        }
+    }
+
+    pub fn case(&self) -> C {
+        self.case
+    }
+}
+
+impl<C: Case> Drop for MultiDoer<C> {
+    fn drop(&mut self) {
+        let group = &self.groups[self.case.index()];
+        if sys::panicking() {
+            group.done_panicked();
+        } else {
+            group.done();
+        }
+    }
+}
+
+impl<C: Case> Clone for MultiDoer<C> {
+    fn clone(&self) -> Self {
+        MultiDoer::new(Arc::clone(&self.groups), self.case)
+    }
+}
+
+impl<C: Case + fmt::Debug> fmt::Debug for MultiDoer<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MultiDoer {{ case {:?} }}", self.case)
+    }
+}
+
+#[must_use]
+pub struct MultiWaiter<C: Case> {
+    groups: Arc<[WaitGroupImpl]>,
+    case: C,
+}
+
+impl<C: Case> MultiWaiter<C> {
+    fn group(&self) -> &WaitGroupImpl {
+        &self.groups[self.case.index()]
+    }
+
+    pub fn wait(&self) -> Result<Generation> {
+        self.group().wait()
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_timeout(&self, timeout: Duration) -> Result<Generation> {
+        self.group().wait_timeout(timeout)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_deadline(&self, deadline: Instant) -> Result<Generation> {
+        self.group().wait_deadline(deadline)
+    }
+
+    pub fn try_wait(&self) -> bool {
+        self.group().try_wait()
+    }
+
+    pub fn case(&self) -> C {
+        self.case
+    }
+}
+
+impl<C: Case> Clone for MultiWaiter<C> {
+    fn clone(&self) -> Self {
+        MultiWaiter {
+            groups: Arc::clone(&self.groups),
+            case: self.case,
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+#[cfg(feature = "std")]
+use std::backtrace::Backtrace;
+
+use crate::lock::Mutex;
+use crate::sys;
+
+const OFF: u8 = 0;
+const LABELS: u8 = 1;
+const BACKTRACES: u8 = 2;
+
+// Live doer of a group which tracks its doers
+#[derive(Clone)]
+pub struct DoerInfo {
+    id: usize,
+    label: Option<Arc<str>>,
+    thread: Option<String>,
+    #[cfg(feature = "std")]
+    backtrace: Option<Arc<Backtrace>>,
+}
+
+impl DoerInfo {
+    // Unique within the group, in order of creation
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    // Name of the creating thread, or its id if the thread is unnamed
+    pub fn thread(&self) -> Option<&str> {
+        self.thread.as_deref()
+    }
+
+    #[cfg(feature = "std")]
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        self.backtrace.as_deref()
+    }
+}
+
+impl fmt::Display for DoerInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.id)?;
+        if let Some(label) = &self.label {
+            write!(f, " `{}`", label)?;
+        }
+        if let Some(thread) = &self.thread {
+            write!(f, " created on thread `{}`", thread)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for DoerInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)?;
+        #[cfg(feature = "std")]
+        if let Some(backtrace) = &self.backtrace {
+            write!(f, " at\n{}", backtrace)?;
+        }
+        Ok(())
+    }
+}
+
+// Doers are registered only if tracking is enabled,
+// otherwise the registry costs a single load per doer
+pub struct Registry {
+    mode: AtomicU8,
+    next_id: AtomicUsize,
+    doers: Mutex<Vec<DoerInfo>>,
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry {
+            mode: AtomicU8::new(OFF),
+            next_id: AtomicUsize::new(0),
+            doers: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn enable(&self, backtraces: bool) {
+        let mode = if backtraces { BACKTRACES } else { LABELS };
+        self.mode.store(mode, Ordering::SeqCst);
+    }
+
+    // Returns id of the registered doer, if tracking is enabled
+    pub fn register(&self, label: Option<Arc<str>>) -> Option<usize> {
+        let mode = self.mode.load(Ordering::SeqCst);
+        if mode == OFF {
+            return None;
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let info = DoerInfo {
+            id,
+            label,
+            thread: sys::current_thread(),
+            #[cfg(feature = "std")]
+            backtrace: if mode == BACKTRACES {
+                Some(Arc::new(Backtrace::force_capture()))
+            } else {
+                None
+            },
+        };
+        self.doers.lock().push(info);
+        Some(id)
+    }
+
+    pub fn label(&self, id: usize) -> Option<Arc<str>> {
+        let doers = self.doers.lock();
+        doers
+            .iter()
+            .find(|info| info.id == id)
+            .and_then(|info| info.label.clone())
+    }
+
+    pub fn set_label(&self, id: usize, label: Arc<str>) {
+        let mut doers = self.doers.lock();
+        if let Some(info) = doers.iter_mut().find(|info| info.id == id) {
+            info.label = Some(label);
+        }
+    }
+
+    pub fn remove(&self, id: usize) {
+        let mut doers = self.doers.lock();
+        if let Some(index) = doers.iter().position(|info| info.id == id) {
+            doers.remove(index);
+        }
+    }
+
+    pub fn outstanding(&self) -> Vec<DoerInfo> {
+        self.doers.lock().clone()
+    }
+}
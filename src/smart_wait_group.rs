@@ -1,144 +1,821 @@
-use std::sync::Arc;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use core::any::Any;
+use core::error::Error;
+use core::future::IntoFuture;
+use core::mem;
+use core::ops::Deref;
+use core::ptr;
+#[cfg(feature = "std")]
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(feature = "std")]
+use std::thread::ThreadId;
 
+use crate::generation::Generation;
+use crate::lock::Mutex;
+#[cfg(feature = "metrics")]
+use crate::metrics::Stats;
+use crate::subscription::Subscription;
+use crate::sys;
+use crate::sys::Instant;
+use crate::wait_future::{Completion, WaitFuture};
 use crate::wait_group_impl::WaitGroupImpl;
+use crate::{DoerError, DoerInfo, Result, WaitGroupError, WaitStrategy};
 
 pub enum Order {
     DoerWaiter,
     WaiterDoer,
 }
 
-#[derive(Clone)]
-pub struct SmartWaitGroup {
-    inner: Arc<WaitGroupImpl>,
+// WaitGroupImpl with values completed by doers,
+// which are handed over to the waiter once the counter is drained
+pub struct SmartWaitGroupImpl<T> {
+    wait_group: WaitGroupImpl,
+    results: Mutex<Vec<T>>,
+    // Payload of the first panic caught in a spawned doer since the poison was cleared
+    #[cfg(feature = "std")]
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+}
+
+impl<T> SmartWaitGroupImpl<T> {
+    fn new(wait_group: WaitGroupImpl) -> Self {
+        SmartWaitGroupImpl {
+            wait_group,
+            results: Mutex::new(Vec::new()),
+            #[cfg(feature = "std")]
+            panic: Mutex::new(None),
+        }
+    }
+
+    fn push(&self, value: T) {
+        self.results.lock().push(value);
+    }
+
+    // Results are taken by the first waiter which observes the drained counter.
+    // A poisoned or failed batch is drained as well, so its values don't leak into the next one.
+    fn take(&self, completion: Result<Generation>) -> Result<Vec<T>> {
+        self.take_generation(completion).map(|(_, results)| results)
+    }
+
+    fn take_generation(&self, completion: Result<Generation>) -> Result<(Generation, Vec<T>)> {
+        match completion {
+            Ok(generation) => Ok((generation, mem::take(&mut *self.results.lock()))),
+            Err(error @ WaitGroupError::Poisoned { .. })
+            | Err(error @ WaitGroupError::Failed { .. }) => {
+                self.results.lock().clear();
+                Err(error)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn take_panic(&self) -> Option<Box<dyn Any + Send>> {
+        self.panic.lock().take()
+    }
+
+    // Later panics only add up to the poison, so the kept payloads stay bounded
+    #[cfg(feature = "std")]
+    fn store_panic(&self, payload: Box<dyn Any + Send>) {
+        self.panic.lock().get_or_insert(payload);
+    }
+
+    fn clear_poison(&self) {
+        #[cfg(feature = "std")]
+        self.panic.lock().take();
+        self.wait_group.clear_poison();
+    }
+}
+
+impl<T> Deref for SmartWaitGroupImpl<T> {
+    type Target = WaitGroupImpl;
+
+    fn deref(&self) -> &WaitGroupImpl {
+        &self.wait_group
+    }
+}
+
+impl<T> Completion for SmartWaitGroupImpl<T> {
+    type Output = Result<Vec<T>>;
+
+    fn wait_group(&self) -> &WaitGroupImpl {
+        &self.wait_group
+    }
+
+    fn complete(&self, generation: Generation) -> Result<Vec<T>> {
+        self.take(self.wait_group.complete(generation))
+    }
+}
+
+pub struct SmartWaitGroup<T = ()> {
+    inner: Arc<SmartWaitGroupImpl<T>>,
 }
 impl SmartWaitGroup {
     pub fn new() -> Self {
-        SmartWaitGroup {
-            inner: Arc::new(WaitGroupImpl::new()),
-        }
+        Self::collecting()
     }
 
     pub fn splitted() -> (Waiter, Doer) {
         Self::new().split()
     }
 
-    pub fn split(self) -> (Waiter, Doer) {
+    pub fn with_strategy<S: WaitStrategy + 'static>(strategy: S) -> Self {
+        Self::collecting_with_strategy(strategy)
+    }
+}
+
+impl<T> SmartWaitGroup<T> {
+    // Group whose doers may complete with values of type T
+    pub fn collecting() -> Self {
+        SmartWaitGroup {
+            inner: Arc::new(SmartWaitGroupImpl::new(WaitGroupImpl::new())),
+        }
+    }
+
+    pub fn collecting_with_strategy<S: WaitStrategy + 'static>(strategy: S) -> Self {
+        let wait_group = WaitGroupImpl::with_strategy(Box::new(strategy));
+        SmartWaitGroup {
+            inner: Arc::new(SmartWaitGroupImpl::new(wait_group)),
+        }
+    }
+
+    pub fn named<N: Into<Arc<str>>>(self, name: N) -> Self {
+        self.inner.set_name(name.into());
+        self
+    }
+
+    pub fn name(&self) -> Option<Arc<str>> {
+        self.inner.name()
+    }
+
+    // Doers created afterwards are registered with their creating thread
+    // and, if requested, backtrace, until they are released
+    pub fn track_doers(self, backtraces: bool) -> Self {
+        self.inner.track_doers(backtraces);
+        self
+    }
+
+    pub fn outstanding(&self) -> Vec<DoerInfo> {
+        self.inner.outstanding()
+    }
+
+    pub fn split(self) -> (Waiter<T>, Doer<T>) {
         (self.waiter(), self.doer())
     }
 
-    pub fn doer(&self) -> Doer {
+    // Panics if the group is closed, try_doer reports it instead
+    pub fn doer(&self) -> Doer<T> {
         Doer::new(Arc::clone(&self.inner))
     }
 
-    pub fn unique_doer(&self) -> Option<Doer> {
+    pub fn try_doer(&self) -> Result<Doer<T>> {
+        Doer::try_new(Arc::clone(&self.inner))
+    }
+
+    pub fn unique_doer(&self) -> Option<Doer<T>> {
         Doer::unique(Arc::clone(&self.inner))
     }
 
-    pub fn waiter(&self) -> Waiter {
+    pub fn waiter(&self) -> Waiter<T> {
         Waiter::new(Arc::clone(&self.inner))
     }
 
+    pub fn subscribe(&self) -> Subscription<SmartWaitGroupImpl<T>> {
+        Subscription::new(Arc::clone(&self.inner))
+    }
+
     pub fn counter(&self) -> usize {
         self.inner.counter()
     }
 
-    pub fn switch_do_wait(&self, second: &SmartWaitGroup) -> Doer {
-        // Ensure that first and second are differ (not an identical allocations)
-        // for avoiding deadlock
-        assert!(!Arc::ptr_eq(&self.inner, &second.inner));
+    pub fn generation(&self) -> Generation {
+        self.inner.generation()
+    }
 
-        let doer = self.doer();
-        second.waiter().wait();
+    #[cfg(feature = "metrics")]
+    pub fn stats(&self) -> Stats {
+        self.inner.stats()
+    }
+
+    pub fn is_poisoned(&self) -> bool {
+        self.inner.is_poisoned()
+    }
+
+    pub fn clear_poison(&self) {
+        self.inner.clear_poison();
+    }
+
+    pub fn errors(&self) -> Vec<DoerError> {
+        self.inner.errors()
+    }
+
+    pub fn clear_errors(&self) {
+        self.inner.clear_errors();
+    }
+
+    #[cfg(feature = "std")]
+    pub fn take_panic(&self) -> Option<Box<dyn Any + Send>> {
+        self.inner.take_panic()
+    }
+
+    pub fn on_complete<F>(&self, callback: F)
+    where
+        F: FnOnce(Result<Generation>) + Send + 'static,
+    {
+        self.inner.on_complete(Box::new(callback));
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.is_cancelled()
+    }
+
+    pub fn close(&self) {
+        self.inner.close();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+
+    // Switches of a group which detects deadlocks register the current thread
+    // as the holder of the doer, and its waits fail with Deadlock instead of blocking
+    // forever once they would close a cycle. Groups not detecting deadlocks break the cycle.
+    // Switched doers sent to other threads should be detached, see Doer::detached.
+    #[cfg(feature = "std")]
+    pub fn detect_deadlocks(self) -> Self {
+        self.inner.set_detect_deadlocks();
+        self
+    }
+
+    // Groups of a hierarchy may only be waited for in decreasing order of levels:
+    // a thread holding a doer switched to can wait only for lower levels.
+    // Waits against the hierarchy fail with LevelViolation instead of deadlocking.
+    // Switches between two leveled groups pick the safe order themselves.
+    #[cfg(feature = "std")]
+    pub fn leveled(self, level: usize) -> Self {
+        self.inner.set_level(level);
+        self
+    }
+
+    #[cfg(feature = "std")]
+    pub fn level(&self) -> Option<usize> {
+        self.inner.level()
+    }
+
+    // Keeps the doer while waiting only if the second group is lower in the hierarchy,
+    // otherwise waits first. Waits of groups out of the hierarchy are never done holding the doer
+    #[cfg(feature = "std")]
+    pub fn switch_by_level<U>(&self, second: &SmartWaitGroup<U>) -> Result<Doer<T>> {
+        self.switch_until(second, Order::WaiterDoer, None)
+    }
+
+    // Safe order of a switch between two groups of the hierarchy
+    #[cfg(feature = "std")]
+    fn order_by_level<U>(&self, second: &SmartWaitGroup<U>) -> Option<Order> {
+        match (self.level()?, second.level()?) {
+            (level, second_level) if level > second_level => Some(Order::DoerWaiter),
+            _ => Some(Order::WaiterDoer),
+        }
+    }
+
+    // Untimed switches panic on a deadlock or a level violation,
+    // their try_ counterparts return the error instead
+    pub fn switch_do_wait<U>(&self, second: &SmartWaitGroup<U>) -> Doer<T> {
+        Self::switched(self.switch_do_wait_until(second, None))
+    }
+
+    pub fn switch_wait_do<U>(&self, second: &SmartWaitGroup<U>) -> Doer<T> {
+        Self::switched(self.switch_wait_do_until(second, None))
+    }
+
+    pub fn switch<U>(&self, second: &SmartWaitGroup<U>, order: Order) -> Doer<T> {
+        Self::switched(self.switch_until(second, order, None))
+    }
+
+    pub fn switch_unique<U>(&self, second: &SmartWaitGroup<U>) -> Option<Doer<T>> {
+        Self::switched(self.switch_unique_until(second, None))
+    }
+
+    pub fn try_switch_do_wait<U>(&self, second: &SmartWaitGroup<U>) -> Result<Doer<T>> {
+        self.switch_do_wait_until(second, None)
+    }
+
+    pub fn try_switch_wait_do<U>(&self, second: &SmartWaitGroup<U>) -> Result<Doer<T>> {
+        self.switch_wait_do_until(second, None)
+    }
+
+    pub fn try_switch<U>(&self, second: &SmartWaitGroup<U>, order: Order) -> Result<Doer<T>> {
+        self.switch_until(second, order, None)
+    }
+
+    pub fn try_switch_unique<U>(&self, second: &SmartWaitGroup<U>) -> Result<Option<Doer<T>>> {
+        self.switch_unique_until(second, None)
+    }
+
+    // Doer is already released on error, so the panic doesn't poison the group
+    fn switched<D>(res: Result<D>) -> D {
+        res.unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    // Poison and failures of the second group are reported to its own waiters,
+    // a switch fails only when the wait itself does
+    fn wait_second<U>(second: &SmartWaitGroup<U>, deadline: Option<Instant>) -> Result<()> {
+        match second.inner.wait_until(deadline) {
+            Ok(_) | Err(WaitGroupError::Poisoned { .. }) | Err(WaitGroupError::Failed { .. }) => {
+                Ok(())
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    // Doer held by the current thread while it waits for the second group
+    fn held_doer(&self) -> Doer<T> {
+        #[allow(unused_mut)]
+        let mut doer = self.doer();
+        #[cfg(feature = "std")]
+        {
+            doer.holder = self.inner.hold();
+        }
         doer
     }
 
-    pub fn switch_wait_do(&self, second: &SmartWaitGroup) -> Doer {
+    fn unique_held_doer(&self) -> Option<Doer<T>> {
+        #[allow(unused_mut)]
+        let mut doer = self.unique_doer()?;
+        #[cfg(feature = "std")]
+        {
+            doer.holder = self.inner.hold();
+        }
+        Some(doer)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn switch_do_wait_timeout<U>(
+        &self,
+        second: &SmartWaitGroup<U>,
+        timeout: Duration,
+    ) -> Result<Doer<T>> {
+        self.switch_do_wait_until(second, sys::deadline_after(timeout))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn switch_wait_do_timeout<U>(
+        &self,
+        second: &SmartWaitGroup<U>,
+        timeout: Duration,
+    ) -> Result<Doer<T>> {
+        self.switch_wait_do_until(second, sys::deadline_after(timeout))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn switch_timeout<U>(
+        &self,
+        second: &SmartWaitGroup<U>,
+        order: Order,
+        timeout: Duration,
+    ) -> Result<Doer<T>> {
+        self.switch_until(second, order, sys::deadline_after(timeout))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn switch_unique_timeout<U>(
+        &self,
+        second: &SmartWaitGroup<U>,
+        timeout: Duration,
+    ) -> Result<Option<Doer<T>>> {
+        self.switch_unique_until(second, sys::deadline_after(timeout))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn switch_do_wait_deadline<U>(
+        &self,
+        second: &SmartWaitGroup<U>,
+        deadline: Instant,
+    ) -> Result<Doer<T>> {
+        self.switch_do_wait_until(second, Some(deadline))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn switch_wait_do_deadline<U>(
+        &self,
+        second: &SmartWaitGroup<U>,
+        deadline: Instant,
+    ) -> Result<Doer<T>> {
+        self.switch_wait_do_until(second, Some(deadline))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn switch_deadline<U>(
+        &self,
+        second: &SmartWaitGroup<U>,
+        order: Order,
+        deadline: Instant,
+    ) -> Result<Doer<T>> {
+        self.switch_until(second, order, Some(deadline))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn switch_unique_deadline<U>(
+        &self,
+        second: &SmartWaitGroup<U>,
+        deadline: Instant,
+    ) -> Result<Option<Doer<T>>> {
+        self.switch_unique_until(second, Some(deadline))
+    }
+
+    // On error the doer is released, so the counter is left unchanged
+    fn switch_do_wait_until<U>(
+        &self,
+        second: &SmartWaitGroup<U>,
+        deadline: Option<Instant>,
+    ) -> Result<Doer<T>> {
         // Ensure that first and second are differ (not an identical allocations)
         // for avoiding deadlock
-        assert!(!Arc::ptr_eq(&self.inner, &second.inner));
+        assert!(!ptr::eq(&**self.inner, &**second.inner));
 
-        second.waiter().wait();
-        let doer = self.doer();
-        doer
+        let doer = self.held_doer();
+        Self::wait_second(second, deadline)?;
+        Ok(doer)
     }
 
-    pub fn switch(&self, second: &SmartWaitGroup, order: Order) -> Doer {
+    fn switch_wait_do_until<U>(
+        &self,
+        second: &SmartWaitGroup<U>,
+        deadline: Option<Instant>,
+    ) -> Result<Doer<T>> {
         // Ensure that first and second are differ (not an identical allocations)
         // for avoiding deadlock
-        assert!(!Arc::ptr_eq(&self.inner, &second.inner));
+        assert!(!ptr::eq(&**self.inner, &**second.inner));
+
+        Self::wait_second(second, deadline)?;
+        Ok(self.held_doer())
+    }
 
+    fn switch_until<U>(
+        &self,
+        second: &SmartWaitGroup<U>,
+        order: Order,
+        deadline: Option<Instant>,
+    ) -> Result<Doer<T>> {
+        // Order asked for is ignored if both groups are leveled
+        #[cfg(feature = "std")]
+        let order = self.order_by_level(second).unwrap_or(order);
         match order {
-            Order::DoerWaiter => self.switch_do_wait(second),
-            Order::WaiterDoer => self.switch_wait_do(second),
+            Order::DoerWaiter => self.switch_do_wait_until(second, deadline),
+            Order::WaiterDoer => self.switch_wait_do_until(second, deadline),
         }
     }
 
-    pub fn switch_unique(&self, second: &SmartWaitGroup) -> Option<Doer> {
+    fn switch_unique_until<U>(
+        &self,
+        second: &SmartWaitGroup<U>,
+        deadline: Option<Instant>,
+    ) -> Result<Option<Doer<T>>> {
         // Ensure that first and second are differ (not an identical allocations)
         // for avoiding deadlock
-        assert!(!Arc::ptr_eq(&self.inner, &second.inner));
-        let doer = self.unique_doer();
-        if let Some(_) = doer {
-            second.waiter().wait();
+        assert!(!ptr::eq(&**self.inner, &**second.inner));
+        let doer = self.unique_held_doer();
+        if doer.is_some() {
+            Self::wait_second(second, deadline)?;
         }
-        doer
+        Ok(doer)
+    }
+}
+
+impl<T> Default for SmartWaitGroup<T> {
+    fn default() -> Self {
+        Self::collecting()
     }
+}
 
-    unsafe fn inner(&self) -> Arc<WaitGroupImpl> {
-        Arc::clone(&self.inner)
+impl<T> Clone for SmartWaitGroup<T> {
+    fn clone(&self) -> Self {
+        SmartWaitGroup {
+            inner: Arc::clone(&self.inner),
+        }
     }
 }
 
 #[must_use]
-pub struct Doer {
-    wait_group: Arc<WaitGroupImpl>,
+pub struct Doer<T = ()> {
+    wait_group: Arc<SmartWaitGroupImpl<T>>,
+    #[cfg(feature = "metrics")]
+    started: Instant,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    // Id in the registry of the group, if it tracks doers
+    record: Option<usize>,
+    // Thread which switched to the doer, if the group detects deadlocks
+    #[cfg(feature = "std")]
+    holder: Option<ThreadId>,
+    // Released as panicked even though the thread isn't panicking
+    panicked: bool,
 }
-impl Doer {
-    fn new(wait_group: Arc<WaitGroupImpl>) -> Self {
+impl<T> Doer<T> {
+    fn new(wait_group: Arc<SmartWaitGroupImpl<T>>) -> Self {
         wait_group.increment();
-        Doer { wait_group }
+        Doer::counted(wait_group)
     }
 
-    fn unique(wait_group: Arc<WaitGroupImpl>) -> Option<Self> {
+    // Doer which is already counted in the group
+    fn counted(wait_group: Arc<SmartWaitGroupImpl<T>>) -> Self {
+        #[cfg(feature = "tracing")]
+        let span = wait_group.doer_span();
+        #[cfg(feature = "tracing")]
+        wait_group.trace_added(&span);
+        Doer {
+            record: wait_group.registry().register(None),
+            #[cfg(feature = "std")]
+            holder: None,
+            panicked: false,
+            wait_group,
+            #[cfg(feature = "metrics")]
+            started: Instant::now(),
+            #[cfg(feature = "tracing")]
+            span,
+        }
+    }
+
+    // Label is reported among the outstanding doers and inherited by clones
+    pub fn labeled<L: Into<Arc<str>>>(self, label: L) -> Self {
+        if let Some(id) = self.record {
+            self.wait_group.registry().set_label(id, label.into());
+        }
+        self
+    }
+
+    // A switched doer is held by the thread which switched. Before handing it
+    // to another thread detach it, so the deadlock graph and the hierarchy don't
+    // blame the switching thread, and let the receiving thread hold it again.
+    #[cfg(feature = "std")]
+    pub fn detached(mut self) -> Self {
+        if let Some(holder) = self.holder.take() {
+            self.wait_group.release(holder);
+        }
+        self
+    }
+
+    // Current thread becomes the holder of the doer
+    #[cfg(feature = "std")]
+    pub fn held(self) -> Self {
+        let mut doer = self.detached();
+        doer.holder = doer.wait_group.hold();
+        doer
+    }
+
+    fn try_new(wait_group: Arc<SmartWaitGroupImpl<T>>) -> Result<Self> {
+        wait_group.try_add(1)?;
+        Ok(Doer::counted(wait_group))
+    }
+
+    fn unique(wait_group: Arc<SmartWaitGroupImpl<T>>) -> Option<Self> {
         if wait_group.increment_if_empty() {
-            Some(Doer { wait_group })
+            Some(Doer::counted(wait_group))
         } else {
             None
         }
     }
 
+    // Value is handed over to the waiter, counter is decremented on drop
+    pub fn complete(self, value: T) {
+        self.wait_group.push(value);
+    }
+
+    pub fn fail<E: Into<Box<dyn Error + Send + Sync>>>(self, error: E) {
+        let error: DoerError = Arc::from(error.into());
+        self.wait_group.record_error(error);
+        // Counter is decremented on drop
+    }
+
+    pub fn finish<E: Into<Box<dyn Error + Send + Sync>>>(
+        self,
+        result: core::result::Result<(), E>,
+    ) {
+        if let Err(error) = result {
+            self.fail(error);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.wait_group.is_cancelled()
+    }
+
+    pub fn check_cancelled(&self) -> Result<()> {
+        self.wait_group.check_cancelled()
+    }
+
+    pub fn wait_cancelled(&self) {
+        self.wait_group.wait_cancelled_until(None);
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_cancelled_timeout(&self, timeout: Duration) -> bool {
+        self.wait_group
+            .wait_cancelled_until(sys::deadline_after(timeout))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_cancelled_deadline(&self, deadline: Instant) -> bool {
+        self.wait_group.wait_cancelled_until(Some(deadline))
+    }
+
+    // Runs f, completes the doer with its value and releases the doer. Payload of
+    // a panic is stored in the group before the doer is released, so the waiter
+    // always finds it.
+    #[cfg(feature = "std")]
+    pub(crate) fn run<F>(mut self, f: F)
+    where
+        F: FnOnce() -> T,
+    {
+        match panic::catch_unwind(AssertUnwindSafe(f)) {
+            Ok(value) => self.complete(value),
+            Err(payload) => {
+                self.wait_group.store_panic(payload);
+                self.panicked = true;
+            }
+        }
+    }
+
     fn done(&self) {
-        self.wait_group.done();
+        if let Some(id) = self.record {
+            self.wait_group.registry().remove(id);
+        }
+        #[cfg(feature = "std")]
+        if let Some(holder) = self.holder {
+            self.wait_group.release(holder);
+        }
+        #[cfg(feature = "metrics")]
+        self.wait_group.record_doer(self.started);
+        let panicking = self.panicked || sys::panicking();
+        if panicking {
+            self.wait_group.done_panicked();
+        } else {
+            self.wait_group.done();
+        }
+        #[cfg(feature = "tracing")]
+        self.wait_group.trace_released(&self.span, panicking);
     }
 }
 
-impl Drop for Doer {
+impl<T> Drop for Doer<T> {
     fn drop(&mut self) {
         self.done();
     }
 }
 
-impl Clone for Doer {
+impl<T> Clone for Doer<T> {
     fn clone(&self) -> Self {
-        Doer::new(Arc::clone(&self.wait_group))
+        self.wait_group.increment_admitted();
+        let doer = Doer::counted(Arc::clone(&self.wait_group));
+        let registry = self.wait_group.registry();
+        if let (Some(id), Some(label)) =
+            (doer.record, self.record.and_then(|id| registry.label(id)))
+        {
+            registry.set_label(id, label);
+        }
+        doer
     }
 }
 
 #[must_use]
-pub struct Waiter {
-    wait_group: Arc<WaitGroupImpl>,
+pub struct Waiter<T = ()> {
+    wait_group: Arc<SmartWaitGroupImpl<T>>,
 }
-impl Waiter {
-    fn new(wait_group: Arc<WaitGroupImpl>) -> Self {
+impl<T> Waiter<T> {
+    fn new(wait_group: Arc<SmartWaitGroupImpl<T>>) -> Self {
         Waiter { wait_group }
     }
 
-    pub fn wait(&self) {
-        self.wait_group.wait();
+    pub fn wait(&self) -> Result<Vec<T>> {
+        self.wait_group.take(self.wait_group.wait())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_timeout(&self, timeout: Duration) -> Result<Vec<T>> {
+        self.wait_group.take(self.wait_group.wait_timeout(timeout))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_deadline(&self, deadline: Instant) -> Result<Vec<T>> {
+        self.wait_group
+            .take(self.wait_group.wait_deadline(deadline))
+    }
+
+    pub fn try_wait(&self) -> bool {
+        self.wait_group.try_wait()
+    }
+
+    // Like wait, along with the generation which released the waiter
+    pub fn wait_generation(&self) -> Result<(Generation, Vec<T>)> {
+        self.wait_group.take_generation(self.wait_group.wait())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_generation_timeout(&self, timeout: Duration) -> Result<(Generation, Vec<T>)> {
+        self.wait_group
+            .take_generation(self.wait_group.wait_timeout(timeout))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_generation_deadline(&self, deadline: Instant) -> Result<(Generation, Vec<T>)> {
+        self.wait_group
+            .take_generation(self.wait_group.wait_deadline(deadline))
+    }
+
+    // Like wait, but re-raises the panic caught in a spawned doer
+    #[cfg(feature = "std")]
+    pub fn wait_or_resume(&self) -> Result<Vec<T>> {
+        let result = self.wait();
+        if let Some(payload) = self.take_panic() {
+            panic::resume_unwind(payload);
+        }
+        result
+    }
+
+    #[cfg(feature = "std")]
+    pub fn take_panic(&self) -> Option<Box<dyn Any + Send>> {
+        self.wait_group.take_panic()
+    }
+
+    pub fn wait_until_at_most(&self, threshold: usize) -> Result<usize> {
+        self.wait_group.wait_at_most_until(threshold, None)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_until_at_most_timeout(&self, threshold: usize, timeout: Duration) -> Result<usize> {
+        self.wait_group
+            .wait_at_most_until(threshold, sys::deadline_after(timeout))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wait_until_at_most_deadline(
+        &self,
+        threshold: usize,
+        deadline: Instant,
+    ) -> Result<usize> {
+        self.wait_group
+            .wait_at_most_until(threshold, Some(deadline))
+    }
+
+    pub fn generation(&self) -> Generation {
+        self.wait_group.generation()
+    }
+
+    pub fn outstanding(&self) -> Vec<DoerInfo> {
+        self.wait_group.outstanding()
+    }
+
+    pub fn wait_async(&self) -> WaitFuture<SmartWaitGroupImpl<T>> {
+        WaitFuture::new(Arc::clone(&self.wait_group))
+    }
+
+    pub fn subscribe(&self) -> Subscription<SmartWaitGroupImpl<T>> {
+        Subscription::new(Arc::clone(&self.wait_group))
+    }
+
+    pub fn is_poisoned(&self) -> bool {
+        self.wait_group.is_poisoned()
+    }
+
+    pub fn clear_poison(&self) {
+        self.wait_group.clear_poison();
+    }
+
+    pub fn errors(&self) -> Vec<DoerError> {
+        self.wait_group.errors()
+    }
+
+    pub fn clear_errors(&self) {
+        self.wait_group.clear_errors();
+    }
+
+    pub fn cancel(&self) {
+        self.wait_group.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.wait_group.is_cancelled()
+    }
+}
+
+impl<T> IntoFuture for Waiter<T> {
+    type Output = Result<Vec<T>>;
+    type IntoFuture = WaitFuture<SmartWaitGroupImpl<T>>;
+
+    fn into_future(self) -> WaitFuture<SmartWaitGroupImpl<T>> {
+        WaitFuture::new(self.wait_group)
     }
 }
 
-impl Clone for Waiter {
+impl<T> Clone for Waiter<T> {
     fn clone(&self) -> Self {
         Waiter::new(Arc::clone(&self.wait_group))
     }
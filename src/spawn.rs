@@ -0,0 +1,47 @@
+use std::thread::{self, JoinHandle, ScopedJoinHandle};
+
+use crate::smart_wait_group::SmartWaitGroup;
+
+// Threads spawned with a doer attached. Value returned by f is completed by the doer,
+// which is released when f returns or panics. A panic poisons the group and its
+// payload is kept for the waiter.
+impl<T: Send + 'static> SmartWaitGroup<T> {
+    pub fn spawn<F>(&self, f: F) -> JoinHandle<()>
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let doer = self.doer();
+        thread::spawn(move || doer.run(f))
+    }
+}
+
+impl<T: Send> SmartWaitGroup<T> {
+    // Threads spawned in the scope may borrow non-'static data,
+    // all of them are joined before scope returns
+    pub fn scope<'env, F, R>(&'env self, f: F) -> R
+    where
+        F: for<'scope> FnOnce(&Scope<'scope, 'env, T>) -> R,
+    {
+        thread::scope(|scope| {
+            f(&Scope {
+                scope,
+                wait_group: self,
+            })
+        })
+    }
+}
+
+pub struct Scope<'scope, 'env: 'scope, T> {
+    scope: &'scope thread::Scope<'scope, 'env>,
+    wait_group: &'scope SmartWaitGroup<T>,
+}
+
+impl<'scope, 'env, T: Send> Scope<'scope, 'env, T> {
+    pub fn spawn<F>(&self, f: F) -> ScopedJoinHandle<'scope, ()>
+    where
+        F: FnOnce() -> T + Send + 'scope,
+    {
+        let doer = self.wait_group.doer();
+        self.scope.spawn(move || doer.run(f))
+    }
+}
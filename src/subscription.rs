@@ -0,0 +1,55 @@
+use alloc::sync::Arc;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use futures_core::Stream;
+
+use crate::wait_future::Completion;
+use crate::wait_group_impl::WaitGroupImpl;
+
+// Yields the current counter first and then the counter after each change.
+// Changes made while the subscriber is busy are coalesced into the latest value.
+// Both as an iterator and as a stream it never ends, unless dropped.
+#[must_use = "subscriptions do nothing unless iterated or polled"]
+pub struct Subscription<W: Completion = WaitGroupImpl> {
+    wait_group: Arc<W>,
+    key: Option<usize>,
+    // Last change seen by the subscriber
+    seen: Option<usize>,
+}
+
+impl<W: Completion> Subscription<W> {
+    pub(crate) fn new(wait_group: Arc<W>) -> Self {
+        wait_group.wait_group().subscribe();
+        Subscription {
+            wait_group,
+            key: None,
+            seen: None,
+        }
+    }
+}
+
+impl<W: Completion> Iterator for Subscription<W> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        Some(self.wait_group.wait_group().next_change(&mut self.seen))
+    }
+}
+
+impl<W: Completion> Stream for Subscription<W> {
+    type Item = usize;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<usize>> {
+        let this = self.get_mut();
+        this.wait_group
+            .wait_group()
+            .poll_change(&mut this.key, &mut this.seen, cx)
+            .map(Some)
+    }
+}
+
+impl<W: Completion> Drop for Subscription<W> {
+    fn drop(&mut self) {
+        self.wait_group.wait_group().unsubscribe(self.key);
+    }
+}
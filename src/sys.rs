@@ -0,0 +1,61 @@
+use core::time::Duration;
+
+// Clock and panic state are only available with std. Without std there are
+// no deadlines at all, so Instant is uninhabited and waits block until the event.
+#[cfg(feature = "std")]
+pub use std::time::Instant;
+
+#[cfg(not(feature = "std"))]
+#[derive(Clone, Copy, Debug)]
+pub enum Instant {}
+
+// None means the deadline is too far to be represented, i.e. infinite
+#[cfg(feature = "std")]
+pub fn deadline_after(timeout: Duration) -> Option<Instant> {
+    Instant::now().checked_add(timeout)
+}
+
+#[cfg(not(feature = "std"))]
+pub fn deadline_after(_timeout: Duration) -> Option<Instant> {
+    None
+}
+
+// None if the deadline has already passed
+#[cfg(feature = "std")]
+pub fn time_left(deadline: Instant) -> Option<Duration> {
+    let now = Instant::now();
+    if now >= deadline {
+        None
+    } else {
+        Some(deadline - now)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+pub fn time_left(deadline: Instant) -> Option<Duration> {
+    match deadline {}
+}
+
+#[cfg(feature = "std")]
+pub fn panicking() -> bool {
+    std::thread::panicking()
+}
+
+#[cfg(not(feature = "std"))]
+pub fn panicking() -> bool {
+    false
+}
+
+#[cfg(feature = "std")]
+pub fn current_thread() -> Option<alloc::string::String> {
+    let thread = std::thread::current();
+    Some(match thread.name() {
+        Some(name) => name.into(),
+        None => alloc::format!("{:?}", thread.id()),
+    })
+}
+
+#[cfg(not(feature = "std"))]
+pub fn current_thread() -> Option<alloc::string::String> {
+    None
+}
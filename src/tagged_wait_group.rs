@@ -0,0 +1,210 @@
+use alloc::boxed::Box;
+use core::error::Error;
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+use crate::smart_wait_group::{Doer, SmartWaitGroup, Waiter};
+use crate::Order;
+
+// SmartWaitGroup branded with a Tag type, so functions can demand TaggedDoer<Normal>
+// and a doer of the group tagged Special doesn't compile there. Tag is only a marker,
+// groups with the same tag are still interchangeable.
+pub struct TaggedWaitGroup<Tag, T = ()> {
+    inner: SmartWaitGroup<T>,
+    tag: PhantomData<fn() -> Tag>,
+}
+
+impl<Tag> TaggedWaitGroup<Tag> {
+    pub fn new() -> Self {
+        SmartWaitGroup::new().tagged()
+    }
+}
+
+impl<Tag, T> TaggedWaitGroup<Tag, T> {
+    pub fn collecting() -> Self {
+        SmartWaitGroup::collecting().tagged()
+    }
+
+    // Untagged group for the rest of the API. Its doers and waiters aren't branded
+    pub fn untagged(&self) -> &SmartWaitGroup<T> {
+        &self.inner
+    }
+
+    pub fn split(self) -> (TaggedWaiter<Tag, T>, TaggedDoer<Tag, T>) {
+        (self.waiter(), self.doer())
+    }
+
+    pub fn doer(&self) -> TaggedDoer<Tag, T> {
+        TaggedDoer::new(self.inner.doer())
+    }
+
+    pub fn unique_doer(&self) -> Option<TaggedDoer<Tag, T>> {
+        self.inner.unique_doer().map(TaggedDoer::new)
+    }
+
+    pub fn waiter(&self) -> TaggedWaiter<Tag, T> {
+        TaggedWaiter::new(self.inner.waiter())
+    }
+
+    pub fn counter(&self) -> usize {
+        self.inner.counter()
+    }
+
+    pub fn switch_do_wait<OtherTag, U>(
+        &self,
+        second: &TaggedWaitGroup<OtherTag, U>,
+    ) -> TaggedDoer<Tag, T> {
+        TaggedDoer::new(self.inner.switch_do_wait(&second.inner))
+    }
+
+    pub fn switch_wait_do<OtherTag, U>(
+        &self,
+        second: &TaggedWaitGroup<OtherTag, U>,
+    ) -> TaggedDoer<Tag, T> {
+        TaggedDoer::new(self.inner.switch_wait_do(&second.inner))
+    }
+
+    pub fn switch<OtherTag, U>(
+        &self,
+        second: &TaggedWaitGroup<OtherTag, U>,
+        order: Order,
+    ) -> TaggedDoer<Tag, T> {
+        TaggedDoer::new(self.inner.switch(&second.inner, order))
+    }
+
+    pub fn switch_unique<OtherTag, U>(
+        &self,
+        second: &TaggedWaitGroup<OtherTag, U>,
+    ) -> Option<TaggedDoer<Tag, T>> {
+        self.inner.switch_unique(&second.inner).map(TaggedDoer::new)
+    }
+}
+
+impl<T> SmartWaitGroup<T> {
+    pub fn tagged<Tag>(self) -> TaggedWaitGroup<Tag, T> {
+        TaggedWaitGroup {
+            inner: self,
+            tag: PhantomData,
+        }
+    }
+}
+
+impl<Tag> Default for TaggedWaitGroup<Tag> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Tag, T> Clone for TaggedWaitGroup<Tag, T> {
+    fn clone(&self) -> Self {
+        self.inner.clone().tagged()
+    }
+}
+
+/// Doer of a tagged group, derefs to the untagged one.
+///
+/// ```
+/// use wait_group::{TaggedDoer, TaggedWaitGroup};
+///
+/// struct Normal;
+///
+/// fn normal_task(_doer: TaggedDoer<Normal>) {}
+///
+/// let normal = TaggedWaitGroup::<Normal>::new();
+/// normal_task(normal.doer());
+/// ```
+///
+/// A doer of a group with another tag doesn't compile:
+///
+/// ```compile_fail
+/// use wait_group::{TaggedDoer, TaggedWaitGroup};
+///
+/// struct Normal;
+/// struct Special;
+///
+/// fn normal_task(_doer: TaggedDoer<Normal>) {}
+///
+/// let special = TaggedWaitGroup::<Special>::new();
+/// normal_task(special.doer());
+/// ```
+#[must_use]
+pub struct TaggedDoer<Tag, T = ()> {
+    inner: Doer<T>,
+    tag: PhantomData<fn() -> Tag>,
+}
+
+impl<Tag, T> TaggedDoer<Tag, T> {
+    fn new(inner: Doer<T>) -> Self {
+        TaggedDoer {
+            inner,
+            tag: PhantomData,
+        }
+    }
+
+    pub fn into_untagged(self) -> Doer<T> {
+        self.inner
+    }
+
+    pub fn complete(self, value: T) {
+        self.inner.complete(value);
+    }
+
+    pub fn fail<E: Into<Box<dyn Error + Send + Sync>>>(self, error: E) {
+        self.inner.fail(error);
+    }
+
+    pub fn finish<E: Into<Box<dyn Error + Send + Sync>>>(
+        self,
+        result: core::result::Result<(), E>,
+    ) {
+        self.inner.finish(result);
+    }
+}
+
+impl<Tag, T> Deref for TaggedDoer<Tag, T> {
+    type Target = Doer<T>;
+
+    fn deref(&self) -> &Doer<T> {
+        &self.inner
+    }
+}
+
+impl<Tag, T> Clone for TaggedDoer<Tag, T> {
+    fn clone(&self) -> Self {
+        TaggedDoer::new(self.inner.clone())
+    }
+}
+
+// Waiter of a tagged group, derefs to the untagged one
+#[must_use]
+pub struct TaggedWaiter<Tag, T = ()> {
+    inner: Waiter<T>,
+    tag: PhantomData<fn() -> Tag>,
+}
+
+impl<Tag, T> TaggedWaiter<Tag, T> {
+    fn new(inner: Waiter<T>) -> Self {
+        TaggedWaiter {
+            inner,
+            tag: PhantomData,
+        }
+    }
+
+    pub fn into_untagged(self) -> Waiter<T> {
+        self.inner
+    }
+}
+
+impl<Tag, T> Deref for TaggedWaiter<Tag, T> {
+    type Target = Waiter<T>;
+
+    fn deref(&self) -> &Waiter<T> {
+        &self.inner
+    }
+}
+
+impl<Tag, T> Clone for TaggedWaiter<Tag, T> {
+    fn clone(&self) -> Self {
+        TaggedWaiter::new(self.inner.clone())
+    }
+}
@@ -0,0 +1,57 @@
+use rayon::iter::plumbing::UnindexedConsumer;
+use rayon::iter::ParallelIterator;
+use rayon::ThreadPool;
+
+use crate::smart_wait_group::{Doer, SmartWaitGroup};
+
+impl<T: Send + 'static> SmartWaitGroup<T> {
+    // Like spawn, but runs f in the pool, so the work can be waited for
+    // from outside of it without pool.scope
+    pub fn spawn_in<F>(&self, pool: &ThreadPool, f: F)
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let doer = self.doer();
+        pool.spawn(move || doer.run(f));
+    }
+}
+
+pub trait DoerParallelIterator: ParallelIterator {
+    // Hands every item a doer of the group, which is taken right before the item
+    // is processed and released as soon as the item is done with it.
+    // The group isn't drained until the iterator is driven to the end.
+    fn with_doers<T: Send>(self, wait_group: &SmartWaitGroup<T>) -> WithDoers<'_, Self, T> {
+        WithDoers {
+            base: self,
+            wait_group,
+        }
+    }
+}
+
+impl<I: ParallelIterator> DoerParallelIterator for I {}
+
+#[must_use]
+pub struct WithDoers<'a, I, T> {
+    base: I,
+    wait_group: &'a SmartWaitGroup<T>,
+}
+
+impl<'a, I, T> ParallelIterator for WithDoers<'a, I, T>
+where
+    I: ParallelIterator,
+    T: Send,
+{
+    type Item = (I::Item, Doer<T>);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        let wait_group = self.wait_group;
+        // Holds the counter above zero between items until the whole iterator is driven
+        let _doer = wait_group.doer();
+        self.base
+            .map(move |item| (item, wait_group.doer()))
+            .drive_unindexed(consumer)
+    }
+}
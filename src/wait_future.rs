@@ -0,0 +1,72 @@
+use alloc::sync::Arc;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use crate::generation::Generation;
+use crate::wait_group_impl::WaitGroupImpl;
+use crate::Result;
+
+// Anything built on top of WaitGroupImpl, which produces its output
+// once the counter is drained
+pub trait Completion {
+    type Output;
+
+    fn wait_group(&self) -> &WaitGroupImpl;
+
+    fn complete(&self, generation: Generation) -> Self::Output;
+}
+
+impl Completion for WaitGroupImpl {
+    type Output = Result<Generation>;
+
+    fn wait_group(&self) -> &WaitGroupImpl {
+        self
+    }
+
+    fn complete(&self, generation: Generation) -> Result<Generation> {
+        self.completion().map(|()| generation)
+    }
+}
+
+// Resolves when the counter is zero. Doesn't block the thread,
+// so it can be awaited concurrently with blocking waiters of the same group.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct WaitFuture<W: Completion = WaitGroupImpl> {
+    wait_group: Arc<W>,
+    key: Option<usize>,
+    // Generation at the first poll
+    start: Option<usize>,
+}
+
+impl<W: Completion> WaitFuture<W> {
+    pub(crate) fn new(wait_group: Arc<W>) -> Self {
+        WaitFuture {
+            wait_group,
+            key: None,
+            start: None,
+        }
+    }
+}
+
+impl<W: Completion> Future for WaitFuture<W> {
+    type Output = W::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<W::Output> {
+        let this = self.get_mut();
+        let wait_group = this.wait_group.wait_group();
+        match wait_group.poll_wait(&mut this.key, &mut this.start, cx) {
+            Poll::Ready(generation) => Poll::Ready(this.wait_group.complete(generation)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<W: Completion> Drop for WaitFuture<W> {
+    fn drop(&mut self) {
+        // Future can be dropped mid-wait, so its waker must not outlive it
+        if let Some(key) = self.key {
+            self.wait_group.wait_group().remove_waker(key);
+        }
+    }
+}
@@ -1,21 +1,139 @@
-use std::fmt;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt;
 
+use crate::registry::DoerInfo;
+
+// Error a doer has finished with
+pub type DoerError = Arc<dyn Error + Send + Sync>;
+
+// Every variant carries the name of the group it came from, if the group is named
+#[derive(Debug)]
 pub enum WaitGroupError {
-    NegativeCounter(isize),
-    Unexpected(String),
+    NegativeCounter {
+        group: Option<Arc<str>>,
+        counter: usize,
+        delta: isize,
+    },
+    CounterOverflow {
+        group: Option<Arc<str>>,
+        counter: usize,
+        delta: isize,
+    },
+    Timeout {
+        group: Option<Arc<str>>,
+        counter: usize,
+        // Live doers, if the group tracks them
+        outstanding: Vec<DoerInfo>,
+    },
+    Poisoned {
+        group: Option<Arc<str>>,
+        panicked: usize,
+    },
+    Failed {
+        group: Option<Arc<str>>,
+        error: DoerError,
+    },
+    Cancelled {
+        group: Option<Arc<str>>,
+    },
+    Closed {
+        group: Option<Arc<str>>,
+    },
+    // Waiting for the group of `level` while holding a doer of `held` level
+    LevelViolation {
+        group: Option<Arc<str>>,
+        level: usize,
+        held: usize,
+    },
+    // Names of the groups waited for in the cycle, starting from this group
+    Deadlock {
+        group: Option<Arc<str>>,
+        cycle: Vec<Option<Arc<str>>>,
+    },
+}
+
+impl WaitGroupError {
+    pub fn group(&self) -> Option<&str> {
+        match self {
+            WaitGroupError::NegativeCounter { group, .. }
+            | WaitGroupError::CounterOverflow { group, .. }
+            | WaitGroupError::Timeout { group, .. }
+            | WaitGroupError::Poisoned { group, .. }
+            | WaitGroupError::Failed { group, .. }
+            | WaitGroupError::Cancelled { group }
+            | WaitGroupError::Closed { group }
+            | WaitGroupError::LevelViolation { group, .. }
+            | WaitGroupError::Deadlock { group, .. } => group.as_deref(),
+        }
+    }
 }
 
-impl fmt::Debug for WaitGroupError {
+impl fmt::Display for WaitGroupError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.group() {
+            Some(group) => write!(f, "wait group `{}`: ", group)?,
+            None => write!(f, "wait group: ")?,
+        }
         match self {
-            WaitGroupError::NegativeCounter(counter) => {
-                write!(f, "Counter is negative: {}", counter)
+            WaitGroupError::NegativeCounter { counter, delta, .. } => write!(
+                f,
+                "counter {} becomes negative after adding {}",
+                counter, delta
+            ),
+            WaitGroupError::CounterOverflow { counter, delta, .. } => {
+                write!(f, "counter {} overflows after adding {}", counter, delta)
             }
-            WaitGroupError::Unexpected(description) => {
-                write!(f, "Unexpected WaitGroupError: {}", description)
+            WaitGroupError::Timeout {
+                counter,
+                outstanding,
+                ..
+            } => {
+                write!(f, "timed out with counter {}", counter)?;
+                for (index, info) in outstanding.iter().enumerate() {
+                    let separator = if index == 0 {
+                        ", outstanding doers: "
+                    } else {
+                        ", "
+                    };
+                    write!(f, "{}{}", separator, info)?;
+                }
+                Ok(())
             }
+            WaitGroupError::Poisoned { panicked, .. } => {
+                write!(f, "poisoned by {} panicked doers", panicked)
+            }
+            WaitGroupError::Failed { error, .. } => write!(f, "doer failed: {}", error),
+            WaitGroupError::Cancelled { .. } => write!(f, "cancelled"),
+            WaitGroupError::Closed { .. } => write!(f, "closed for new doers"),
+            WaitGroupError::LevelViolation { level, held, .. } => write!(
+                f,
+                "waiting for level {} while holding a doer of level {}",
+                level, held
+            ),
+            WaitGroupError::Deadlock { cycle, .. } => {
+                write!(f, "deadlock on groups ")?;
+                for (index, group) in cycle.iter().enumerate() {
+                    let separator = if index == 0 { "" } else { " -> " };
+                    match group {
+                        Some(group) => write!(f, "{}`{}`", separator, group)?,
+                        None => write!(f, "{}unnamed", separator)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for WaitGroupError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WaitGroupError::Failed { error, .. } => Some(&**error),
+            _ => None,
         }
     }
 }
 
-pub type Result<T> = std::result::Result<T, WaitGroupError>;
+pub type Result<T> = core::result::Result<T, WaitGroupError>;
@@ -1,35 +1,560 @@
-use std::fmt;
-use std::sync::{Condvar, Mutex};
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::mem;
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::AtomicU64;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::task::{Context, Poll};
+use core::time::Duration;
 
-use crate::{Result, WaitGroupError};
+#[cfg(feature = "std")]
+use crate::deadlock;
+use crate::event::Event;
+use crate::generation::Generation;
+#[cfg(feature = "std")]
+use crate::hierarchy;
+use crate::lock::Mutex;
+#[cfg(feature = "metrics")]
+use crate::metrics::{Metrics, Stats};
+use crate::registry::{DoerInfo, Registry};
+use crate::sys::{self, Instant};
+use crate::wait_strategy::{default_strategy, WaitStrategy};
+use crate::waker_list::WakerList;
+use crate::{DoerError, Result, WaitGroupError};
 
+pub type Callback = Box<dyn FnOnce(Result<Generation>) + Send>;
+
+#[cfg(feature = "std")]
+const NO_LEVEL: usize = usize::MAX;
+
+// Targets without 64-bit atomics pack the state into a narrower word,
+// trading the generation range for the lock-free counter
+#[cfg(target_has_atomic = "64")]
+type Word = u64;
+#[cfg(target_has_atomic = "64")]
+type AtomicWord = AtomicU64;
+#[cfg(target_has_atomic = "64")]
+const COUNTER_BITS: u32 = 32;
+
+#[cfg(not(target_has_atomic = "64"))]
+type Word = usize;
+#[cfg(not(target_has_atomic = "64"))]
+type AtomicWord = AtomicUsize;
+#[cfg(not(target_has_atomic = "64"))]
+const COUNTER_BITS: u32 = 20;
+
+const COUNTER_MASK: Word = (1 << COUNTER_BITS) - 1;
+const GENERATION_MASK: Word = Word::MAX >> COUNTER_BITS;
+
+// Counter is limited to the lower part of the state word: u32::MAX even on 64-bit
+// targets, 2^20 - 1 on targets without 64-bit atomics
+pub const MAX_COUNTER: usize = COUNTER_MASK as usize;
+
+// Counter in the lower part and generation in the upper part of a single word,
+// so the drop to zero and the start of the next generation are seen at once
+#[derive(Clone, Copy)]
+struct State(Word);
+
+impl State {
+    fn counter(self) -> usize {
+        (self.0 & COUNTER_MASK) as usize
+    }
+
+    fn generation(self) -> usize {
+        (self.0 >> COUNTER_BITS) as usize
+    }
+
+    // Only a drop from a positive counter to zero starts the next generation
+    fn with_counter(self, counter: usize) -> State {
+        let generation = if counter == 0 && self.counter() != 0 {
+            next_generation(self.generation())
+        } else {
+            self.generation()
+        };
+        State((generation as Word) << COUNTER_BITS | counter as Word)
+    }
+}
+
+// Generations wrap around within the upper part of the state word
+fn next_generation(generation: usize) -> usize {
+    ((generation as Word).wrapping_add(1) & GENERATION_MASK) as usize
+}
+
+// Counter lives in a single atomic word together with the generation,
+// so add/done never take a lock.
+// Waiters sleep on the event, which is bumped each time the counter drops to zero.
+// Every drop to zero starts a new generation, so a waiter is released by the first
+// generation change after it started waiting, whatever the counter is at wake up.
+// Async waiters are registered in the waker list, which is locked only
+// if there are pending futures. Cancellation bumps the event too,
+// so doers may sleep on it until the group is cancelled.
 pub struct WaitGroupImpl {
-    counter: Mutex<usize>,
-    condition: Condvar,
+    state: AtomicWord,
+    event: Event,
+    wakers: Mutex<WakerList>,
+    pending_wakers: AtomicUsize,
+    // Number of doers released during panic
+    panicked: AtomicUsize,
+    cancelled: AtomicBool,
+    // Errors of failed doers in order of failing
+    errors: Mutex<Vec<DoerError>>,
+    failed: AtomicUsize,
+    // Closed group rejects new doers
+    closed: AtomicBool,
+    // Thresholds of blocked `wait_at_most` callers, with their count for the fast path
+    thresholds: Mutex<Vec<usize>>,
+    threshold_waiters: AtomicUsize,
+    // Counter changes, subscribers compare it with the last change they've seen
+    changes: AtomicUsize,
+    subscribers: AtomicUsize,
+    watchers: Mutex<WakerList>,
+    // Run once by the thread which drains the counter
+    callbacks: Mutex<Vec<Callback>>,
+    pending_callbacks: AtomicUsize,
+    name: Mutex<Option<Arc<str>>>,
+    registry: Registry,
+    #[cfg(feature = "std")]
+    detect_deadlocks: AtomicBool,
+    // Position in the lock hierarchy, NO_LEVEL if the group is out of it
+    #[cfg(feature = "std")]
+    level: AtomicUsize,
+    #[cfg(feature = "metrics")]
+    metrics: Metrics,
 }
 
 impl WaitGroupImpl {
     pub fn new() -> WaitGroupImpl {
+        Self::with_strategy(default_strategy())
+    }
+
+    pub fn with_strategy(strategy: Box<dyn WaitStrategy>) -> WaitGroupImpl {
         WaitGroupImpl {
-            counter: Mutex::new(0),
-            condition: Condvar::new(),
+            state: AtomicWord::new(0),
+            event: Event::new(strategy),
+            wakers: Mutex::new(WakerList::new()),
+            pending_wakers: AtomicUsize::new(0),
+            panicked: AtomicUsize::new(0),
+            cancelled: AtomicBool::new(false),
+            errors: Mutex::new(Vec::new()),
+            failed: AtomicUsize::new(0),
+            closed: AtomicBool::new(false),
+            thresholds: Mutex::new(Vec::new()),
+            threshold_waiters: AtomicUsize::new(0),
+            changes: AtomicUsize::new(0),
+            subscribers: AtomicUsize::new(0),
+            watchers: Mutex::new(WakerList::new()),
+            callbacks: Mutex::new(Vec::new()),
+            pending_callbacks: AtomicUsize::new(0),
+            name: Mutex::new(None),
+            registry: Registry::new(),
+            #[cfg(feature = "std")]
+            detect_deadlocks: AtomicBool::new(false),
+            #[cfg(feature = "std")]
+            level: AtomicUsize::new(NO_LEVEL),
+            #[cfg(feature = "metrics")]
+            metrics: Metrics::new(),
+        }
+    }
+
+    pub fn wait(&self) -> Result<Generation> {
+        self.wait_until(None)
+    }
+
+    pub fn wait_timeout(&self, timeout: Duration) -> Result<Generation> {
+        self.wait_until(sys::deadline_after(timeout))
+    }
+
+    pub fn wait_deadline(&self, deadline: Instant) -> Result<Generation> {
+        self.wait_until(Some(deadline))
+    }
+
+    pub fn wait_until(&self, deadline: Option<Instant>) -> Result<Generation> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("wait", group = self.name().as_deref()).entered();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(counter = self.counter(), "waiting");
+        #[cfg(feature = "metrics")]
+        let started = Instant::now();
+        let res = self.wait_released(deadline);
+        #[cfg(feature = "metrics")]
+        self.metrics.record_wait(started.elapsed());
+        #[cfg(feature = "tracing")]
+        match &res {
+            Ok(generation) => tracing::debug!(generation = generation.value(), "released"),
+            Err(error) => tracing::debug!(%error, "wait failed"),
+        }
+        res
+    }
+
+    fn wait_released(&self, deadline: Option<Instant>) -> Result<Generation> {
+        #[cfg(feature = "std")]
+        self.check_level()?;
+        #[cfg(feature = "std")]
+        let _edge = self.wait_edge()?;
+        let start = self.state().generation();
+        loop {
+            // Load event word before the counter: if the counter drops to zero
+            // after this point, the word differs and event wait returns immediately
+            let epoch = self.event.load();
+            if let Some(generation) = self.observe(start) {
+                return self.completion().map(|()| generation);
+            }
+            let count = self.state().counter();
+            let timeout = match deadline {
+                Some(deadline) => match sys::time_left(deadline) {
+                    Some(timeout) => Some(timeout),
+                    None => {
+                        return Err(WaitGroupError::Timeout {
+                            group: self.name(),
+                            counter: count,
+                            outstanding: self.outstanding(),
+                        })
+                    }
+                },
+                None => None,
+            };
+            self.event.wait(epoch, timeout);
+        }
+    }
+
+    // Waits until the counter is at most `threshold`, returns the observed counter
+    pub fn wait_at_most_until(&self, threshold: usize, deadline: Option<Instant>) -> Result<usize> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("wait_at_most", group = self.name().as_deref(), threshold)
+            .entered();
+        // Registered before the counter is checked, so a decrement either sees
+        // the threshold or happens before the check
+        self.thresholds.lock().push(threshold);
+        self.threshold_waiters.fetch_add(1, Ordering::SeqCst);
+        let res = loop {
+            let epoch = self.event.load();
+            let count = self.state().counter();
+            if count <= threshold {
+                break self.completion().map(|()| count);
+            }
+            let timeout = match deadline {
+                Some(deadline) => match sys::time_left(deadline) {
+                    Some(timeout) => Some(timeout),
+                    None => {
+                        break Err(WaitGroupError::Timeout {
+                            group: self.name(),
+                            counter: count,
+                            outstanding: self.outstanding(),
+                        })
+                    }
+                },
+                None => None,
+            };
+            self.event.wait(epoch, timeout);
+        };
+        {
+            let mut thresholds = self.thresholds.lock();
+            if let Some(index) = thresholds.iter().position(|&t| t == threshold) {
+                thresholds.swap_remove(index);
+            }
+            self.threshold_waiters.fetch_sub(1, Ordering::SeqCst);
+        }
+        res
+    }
+
+    pub fn subscribe(&self) {
+        self.subscribers.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn unsubscribe(&self, key: Option<usize>) {
+        if let Some(key) = key {
+            self.watchers.lock().remove(key);
+        }
+        self.subscribers.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    // Counter after the changes not seen yet, several changes are coalesced into one
+    fn observe_change(&self, seen: &mut Option<usize>) -> Option<usize> {
+        let changes = self.changes.load(Ordering::SeqCst);
+        if *seen == Some(changes) {
+            return None;
+        }
+        *seen = Some(changes);
+        Some(self.state().counter())
+    }
+
+    pub fn next_change(&self, seen: &mut Option<usize>) -> usize {
+        loop {
+            let epoch = self.event.load();
+            if let Some(count) = self.observe_change(seen) {
+                return count;
+            }
+            self.event.wait(epoch, None);
+        }
+    }
+
+    pub fn poll_change(
+        &self,
+        key: &mut Option<usize>,
+        seen: &mut Option<usize>,
+        cx: &mut Context<'_>,
+    ) -> Poll<usize> {
+        if let Some(count) = self.observe_change(seen) {
+            return Poll::Ready(count);
+        }
+        self.watchers.lock().register(key, cx.waker());
+        // Check again after registration, otherwise the change could be missed
+        match self.observe_change(seen) {
+            Some(count) => Poll::Ready(count),
+            None => Poll::Pending,
+        }
+    }
+
+    // Callback is run once the counter drops to zero, on the thread releasing the last doer.
+    // Callback of an empty group is run at once by the caller
+    pub fn on_complete(&self, callback: Callback) {
+        {
+            let mut callbacks = self.callbacks.lock();
+            callbacks.push(callback);
+            self.pending_callbacks.fetch_add(1, Ordering::SeqCst);
+        }
+        // Counter could drop to zero before the registration, then nobody else runs it
+        let state = self.state();
+        if state.counter() == 0 {
+            self.run_callbacks(Generation::new(state.generation()));
+        }
+    }
+
+    fn run_callbacks(&self, generation: Generation) {
+        let callbacks = {
+            let mut callbacks = self.callbacks.lock();
+            self.pending_callbacks
+                .fetch_sub(callbacks.len(), Ordering::SeqCst);
+            mem::take(&mut *callbacks)
+        };
+        // Run outside of the lock, callbacks may register new ones
+        for callback in callbacks {
+            callback(self.completion().map(|()| generation));
+        }
+    }
+
+    pub fn cancel(&self) {
+        if !self.cancelled.swap(true, Ordering::SeqCst) {
+            self.event.bump();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    // Lets doers bail out of cancelled work with `?`
+    pub fn check_cancelled(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(WaitGroupError::Cancelled { group: self.name() })
+        } else {
+            Ok(())
+        }
+    }
+
+    // New doers are rejected afterwards, already added ones and their clones are waited as usual
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    // Name is reported in errors and Debug output of the group
+    pub fn set_name(&self, name: Arc<str>) {
+        *self.name.lock() = Some(name);
+    }
+
+    pub fn name(&self) -> Option<Arc<str>> {
+        self.name.lock().clone()
+    }
+
+    #[cfg(feature = "std")]
+    pub fn set_detect_deadlocks(&self) {
+        self.detect_deadlocks.store(true, Ordering::SeqCst);
+    }
+
+    #[cfg(feature = "std")]
+    pub fn detects_deadlocks(&self) -> bool {
+        self.detect_deadlocks.load(Ordering::SeqCst)
+    }
+
+    #[cfg(feature = "std")]
+    fn id(&self) -> usize {
+        self as *const WaitGroupImpl as usize
+    }
+
+    #[cfg(feature = "std")]
+    pub fn set_level(&self, level: usize) {
+        assert_ne!(level, NO_LEVEL, "Level {} is reserved", NO_LEVEL);
+        self.level.store(level, Ordering::SeqCst);
+    }
+
+    #[cfg(feature = "std")]
+    pub fn level(&self) -> Option<usize> {
+        match self.level.load(Ordering::SeqCst) {
+            NO_LEVEL => None,
+            level => Some(level),
+        }
+    }
+
+    // Current thread switched to a doer of the group, returns the holder to release
+    #[cfg(feature = "std")]
+    pub fn hold(&self) -> Option<std::thread::ThreadId> {
+        let mut holder = None;
+        if self.detects_deadlocks() {
+            holder = Some(deadlock::hold(self.id()));
+        }
+        if let Some(level) = self.level() {
+            holder = Some(hierarchy::hold(level));
+        }
+        holder
+    }
+
+    #[cfg(feature = "std")]
+    pub fn release(&self, holder: std::thread::ThreadId) {
+        deadlock::release(self.id(), holder);
+        if let Some(level) = self.level() {
+            hierarchy::release(holder, level);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn check_level(&self) -> Result<()> {
+        let level = match self.level() {
+            Some(level) => level,
+            None => return Ok(()),
+        };
+        hierarchy::check(level).map_err(|held| WaitGroupError::LevelViolation {
+            group: self.name(),
+            level,
+            held,
+        })
+    }
+
+    #[cfg(feature = "std")]
+    fn wait_edge(&self) -> Result<Option<deadlock::WaitEdge>> {
+        if !self.detects_deadlocks() {
+            return Ok(None);
         }
+        match deadlock::wait_for(self.id(), self.name()) {
+            Ok(edge) => Ok(Some(edge)),
+            Err(cycle) => Err(WaitGroupError::Deadlock {
+                group: self.name(),
+                cycle,
+            }),
+        }
+    }
+
+    pub fn track_doers(&self, backtraces: bool) {
+        self.registry.enable(backtraces);
     }
 
-    pub fn wait(&self) {
-        let mut count = self.counter.lock().unwrap();
-        while *count > 0 {
-            count = self.condition.wait(count).unwrap();
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    pub fn outstanding(&self) -> Vec<DoerInfo> {
+        self.registry.outstanding()
+    }
+
+    // Returns true if the group was cancelled before the deadline
+    pub fn wait_cancelled_until(&self, deadline: Option<Instant>) -> bool {
+        loop {
+            let epoch = self.event.load();
+            if self.is_cancelled() {
+                return true;
+            }
+            let timeout = match deadline {
+                Some(deadline) => match sys::time_left(deadline) {
+                    Some(timeout) => Some(timeout),
+                    None => return false,
+                },
+                None => None,
+            };
+            self.event.wait(epoch, timeout);
+        }
+    }
+
+    // Generation the waiter started at `start` is released by, if any
+    fn observe(&self, start: usize) -> Option<Generation> {
+        let state = self.state();
+        if state.generation() != start {
+            // Counter has dropped to zero since start, maybe several times
+            Some(Generation::new(next_generation(start)))
+        } else if state.counter() == 0 {
+            Some(Generation::new(start))
+        } else {
+            None
+        }
+    }
+
+    pub fn poll_wait(
+        &self,
+        key: &mut Option<usize>,
+        start: &mut Option<usize>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Generation> {
+        let start = *start.get_or_insert_with(|| self.state().generation());
+        if let Some(generation) = self.observe(start) {
+            return Poll::Ready(generation);
+        }
+        {
+            let mut wakers = self.wakers.lock();
+            if wakers.register(key, cx.waker()) {
+                self.pending_wakers.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        // Check again after registration, otherwise zero could be missed
+        match self.observe(start) {
+            Some(generation) => Poll::Ready(generation),
+            None => Poll::Pending,
+        }
+    }
+
+    pub fn remove_waker(&self, key: usize) {
+        let mut wakers = self.wakers.lock();
+        if wakers.remove(key) {
+            self.pending_wakers.fetch_sub(1, Ordering::SeqCst);
         }
     }
 
     pub fn add_if_empty(&self, delta: usize) -> bool {
-        let mut count = self.counter.lock().unwrap();
-        if *count != 0 {
-            return false;
+        self.try_add_if_empty(delta).unwrap_or(false)
+    }
+
+    // Starts from zero, so the counter overflows only if delta itself is too large
+    pub fn try_add_if_empty(&self, delta: usize) -> Result<bool> {
+        if self.is_closed() {
+            return Err(WaitGroupError::Closed { group: self.name() });
+        }
+        if delta > MAX_COUNTER {
+            return Err(WaitGroupError::CounterOverflow {
+                group: self.name(),
+                counter: 0,
+                delta: delta.min(isize::MAX as usize) as isize,
+            });
         }
-        *count += delta;
-        true
+        let state = self.state();
+        let added = state.counter() == 0
+            && self
+                .state
+                .compare_exchange(
+                    state.0,
+                    state.with_counter(delta).0,
+                    Ordering::SeqCst,
+                    Ordering::SeqCst,
+                )
+                .is_ok();
+        if added {
+            #[cfg(feature = "metrics")]
+            self.metrics.record_added(delta, delta);
+            self.notify_change();
+        }
+        Ok(added)
     }
 
     pub fn increment_if_empty(&self) -> bool {
@@ -37,32 +562,81 @@ impl WaitGroupImpl {
     }
 
     pub fn increment(&self) {
-        self.add_unchecked(1);
+        self.try_add(1).unwrap();
+    }
+
+    // Clones extend work which is already admitted, so they're counted even in a closed group
+    pub fn increment_admitted(&self) {
+        self.try_add_admitted(1).unwrap();
     }
 
     pub fn add(&self, delta: isize) {
         self.try_add(delta).unwrap();
     }
 
-    #[must_use]
+    // Delta is applied to the unsigned counter without casting it,
+    // so neither a large counter nor isize::MIN can wrap
     pub fn try_add(&self, delta: isize) -> Result<()> {
-        let mut count = self.counter.lock().unwrap();
-        let res = *count as isize + delta;
-        if res < 0 {
-            Err(WaitGroupError::NegativeCounter(res))
-        } else {
-            *count = res as usize;
-            self.notify_if_empty(*count);
-            Ok(())
+        if delta > 0 && self.is_closed() {
+            return Err(WaitGroupError::Closed { group: self.name() });
         }
+        self.try_add_admitted(delta)
     }
 
-    pub fn add_unchecked(&self, delta: usize) {
-        let mut count = self.counter.lock().unwrap();
-        *count += delta;
+    fn try_add_admitted(&self, delta: isize) -> Result<()> {
+        let mut state = self.state();
+        loop {
+            let count = state.counter();
+            let res = if delta >= 0 {
+                count
+                    .checked_add(delta.unsigned_abs())
+                    .filter(|&res| res <= MAX_COUNTER)
+            } else {
+                count.checked_sub(delta.unsigned_abs())
+            };
+            let res = match res {
+                Some(res) => res,
+                None if delta >= 0 => {
+                    return Err(WaitGroupError::CounterOverflow {
+                        group: self.name(),
+                        counter: count,
+                        delta,
+                    })
+                }
+                None => {
+                    return Err(WaitGroupError::NegativeCounter {
+                        group: self.name(),
+                        counter: count,
+                        delta,
+                    })
+                }
+            };
+            let next = state.with_counter(res);
+            match self.state.compare_exchange_weak(
+                state.0,
+                next.0,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => {
+                    #[cfg(feature = "metrics")]
+                    if delta > 0 {
+                        self.metrics.record_added(delta.unsigned_abs(), res);
+                    }
+                    self.notify_change();
+                    if delta < 0 {
+                        self.notify_threshold(res);
+                    }
+                    if next.generation() != state.generation() {
+                        self.notify_drained(next);
+                    }
+                    return Ok(());
+                }
+                Err(actual) => state = State(actual),
+            }
+        }
     }
 
-    #[must_use]
     pub fn try_done(&self) -> Result<()> {
         self.try_add(-1)
     }
@@ -71,20 +645,182 @@ impl WaitGroupImpl {
         self.try_done().unwrap();
     }
 
-    pub fn notify_if_empty(&self, count: usize) {
-        if count == 0 {
-            self.condition.notify_all();
+    // Poison is recorded before the decrement, so waiters released by it see the poison
+    pub fn done_panicked(&self) {
+        self.panicked.fetch_add(1, Ordering::SeqCst);
+        self.done();
+    }
+
+    pub fn is_poisoned(&self) -> bool {
+        self.panicked.load(Ordering::SeqCst) > 0
+    }
+
+    pub fn clear_poison(&self) {
+        self.panicked.store(0, Ordering::SeqCst);
+    }
+
+    // Must be called before the decrement of the failed doer, as well as poison
+    pub fn record_error(&self, error: DoerError) {
+        self.errors.lock().push(error);
+        self.failed.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn errors(&self) -> Vec<DoerError> {
+        self.errors.lock().clone()
+    }
+
+    pub fn clear_errors(&self) {
+        let mut errors = self.errors.lock();
+        errors.clear();
+        self.failed.store(0, Ordering::SeqCst);
+    }
+
+    // Outcome of the drained group: poison takes precedence over the first error
+    pub fn completion(&self) -> Result<()> {
+        match self.panicked.load(Ordering::SeqCst) {
+            0 => {}
+            panicked => {
+                return Err(WaitGroupError::Poisoned {
+                    group: self.name(),
+                    panicked,
+                })
+            }
+        }
+        if self.failed.load(Ordering::SeqCst) == 0 {
+            return Ok(());
+        }
+        match self.errors.lock().first() {
+            Some(error) => Err(WaitGroupError::Failed {
+                group: self.name(),
+                error: Arc::clone(error),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    // Generation is already started by the drop to zero
+    fn notify_drained(&self, state: State) {
+        self.event.bump();
+        if self.pending_wakers.load(Ordering::SeqCst) > 0 {
+            self.wake_futures();
+        }
+        if self.pending_callbacks.load(Ordering::SeqCst) > 0 {
+            self.run_callbacks(Generation::new(state.generation()));
+        }
+    }
+
+    fn notify_change(&self) {
+        self.changes.fetch_add(1, Ordering::SeqCst);
+        if self.subscribers.load(Ordering::SeqCst) > 0 {
+            self.event.bump();
+            let wakers = self.watchers.lock().take_all();
+            for waker in wakers {
+                waker.wake();
+            }
         }
     }
 
+    // Zero is notified by notify_drained anyway
+    fn notify_threshold(&self, count: usize) {
+        if count != 0
+            && self.threshold_waiters.load(Ordering::SeqCst) > 0
+            && self.thresholds.lock().iter().any(|&t| count <= t)
+        {
+            self.event.bump();
+        }
+    }
+
+    fn wake_futures(&self) {
+        let wakers = {
+            let mut wakers = self.wakers.lock();
+            let taken = wakers.take_all();
+            self.pending_wakers.fetch_sub(taken.len(), Ordering::SeqCst);
+            taken
+        };
+        // Wake outside of the lock, wakers may poll the future in place
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    // Acquire pairs with the release part of the decrements,
+    // so everything done before the last `done` is visible after success
+    pub fn try_wait(&self) -> bool {
+        State(self.state.load(Ordering::Acquire)).counter() == 0
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn stats(&self) -> Stats {
+        self.metrics.stats()
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn record_doer(&self, started: Instant) {
+        self.metrics.record_doer(started.elapsed());
+    }
+
+    // Span lives as long as the doer, so it's open for every stuck doer
+    #[cfg(feature = "tracing")]
+    pub fn doer_span(&self) -> tracing::Span {
+        tracing::trace_span!("doer", group = self.name().as_deref())
+    }
+
+    #[cfg(feature = "tracing")]
+    pub fn trace_added(&self, span: &tracing::Span) {
+        span.in_scope(|| tracing::trace!(counter = self.counter(), "doer added"));
+    }
+
+    #[cfg(feature = "tracing")]
+    pub fn trace_released(&self, span: &tracing::Span, panicking: bool) {
+        span.in_scope(|| {
+            if panicking {
+                tracing::warn!(counter = self.counter(), "doer released during panic");
+            } else {
+                tracing::trace!(counter = self.counter(), "doer released");
+            }
+        });
+    }
+
+    #[cfg(feature = "tracing")]
+    pub fn trace_add(&self, delta: isize, res: &Result<()>) {
+        let group = self.name();
+        match res {
+            Ok(()) => tracing::trace!(
+                group = group.as_deref(),
+                delta,
+                counter = self.counter(),
+                "counter changed"
+            ),
+            Err(error) => {
+                tracing::warn!(group = group.as_deref(), delta, %error, "counter not changed")
+            }
+        }
+    }
+
+    pub fn generation(&self) -> Generation {
+        Generation::new(self.state().generation())
+    }
+
+    // Just a snapshot, no synchronization with doers
     pub fn counter(&self) -> usize {
-        *self.counter.lock().unwrap()
+        State(self.state.load(Ordering::Relaxed)).counter()
+    }
+
+    fn state(&self) -> State {
+        State(self.state.load(Ordering::SeqCst))
     }
 }
 
 impl fmt::Debug for WaitGroupImpl {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let count = self.counter.lock().unwrap();
-        write!(f, "WaitGroup {{ count {:?} }}", *count)
+        match self.name() {
+            Some(name) => write!(
+                f,
+                "WaitGroup {{ name {:?}, count {:?} }}",
+                name,
+                self.counter()
+            ),
+            None => write!(f, "WaitGroup {{ count {:?} }}", self.counter()),
+        }
     }
 }
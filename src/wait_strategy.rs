@@ -0,0 +1,222 @@
+use alloc::boxed::Box;
+#[cfg(target_os = "linux")]
+use core::convert::TryFrom;
+use core::hint;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::sync::{Condvar, Mutex};
+#[cfg(feature = "std")]
+use std::thread::{self, Thread};
+
+use crate::sys::{self, Instant};
+
+// How waiting threads are blocked until the counter changes.
+// `wait` must return once `word` differs from `expected`, but it is allowed
+// to return earlier, because callers always check the word again.
+// `wake_all` is called after `word` has been changed.
+pub trait WaitStrategy: Send + Sync {
+    fn wait(&self, word: &AtomicU32, expected: u32, timeout: Option<Duration>);
+
+    fn wake_all(&self, word: &AtomicU32);
+}
+
+// Futex on Linux, parking elsewhere, spinning without std
+pub fn default_strategy() -> Box<dyn WaitStrategy> {
+    #[cfg(target_os = "linux")]
+    let strategy = FutexStrategy;
+    #[cfg(all(not(target_os = "linux"), feature = "std"))]
+    let strategy = ParkStrategy::new();
+    #[cfg(all(not(target_os = "linux"), not(feature = "std")))]
+    let strategy = SpinStrategy;
+    Box::new(strategy)
+}
+
+fn deadline_after(timeout: Option<Duration>) -> Option<Instant> {
+    timeout.and_then(sys::deadline_after)
+}
+
+fn is_expired(deadline: Option<Instant>) -> bool {
+    deadline.is_some_and(|deadline| sys::time_left(deadline).is_none())
+}
+
+// Busy loop, never leaves the CPU
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SpinStrategy;
+
+impl WaitStrategy for SpinStrategy {
+    fn wait(&self, word: &AtomicU32, expected: u32, timeout: Option<Duration>) {
+        let deadline = deadline_after(timeout);
+        while word.load(Ordering::SeqCst) == expected && !is_expired(deadline) {
+            hint::spin_loop();
+        }
+    }
+
+    fn wake_all(&self, _word: &AtomicU32) {}
+}
+
+// Busy loop for a number of iterations, then yields the CPU between checks
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug)]
+pub struct SpinThenYieldStrategy {
+    spins: u32,
+}
+
+#[cfg(feature = "std")]
+impl SpinThenYieldStrategy {
+    pub fn new(spins: u32) -> Self {
+        SpinThenYieldStrategy { spins }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for SpinThenYieldStrategy {
+    fn default() -> Self {
+        Self::new(100)
+    }
+}
+
+#[cfg(feature = "std")]
+impl WaitStrategy for SpinThenYieldStrategy {
+    fn wait(&self, word: &AtomicU32, expected: u32, timeout: Option<Duration>) {
+        let deadline = deadline_after(timeout);
+        let mut spins = 0;
+        while word.load(Ordering::SeqCst) == expected && !is_expired(deadline) {
+            if spins < self.spins {
+                spins += 1;
+                hint::spin_loop();
+            } else {
+                thread::yield_now();
+            }
+        }
+    }
+
+    fn wake_all(&self, _word: &AtomicU32) {}
+}
+
+// Parks waiting threads, which are registered in a list
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct ParkStrategy {
+    parked: Mutex<Vec<Thread>>,
+}
+
+#[cfg(feature = "std")]
+impl ParkStrategy {
+    pub fn new() -> Self {
+        ParkStrategy {
+            parked: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl WaitStrategy for ParkStrategy {
+    fn wait(&self, word: &AtomicU32, expected: u32, timeout: Option<Duration>) {
+        let current = thread::current();
+        {
+            // Checked under the lock, so wake_all can't run in between
+            let mut parked = self.parked.lock().unwrap();
+            if word.load(Ordering::SeqCst) != expected {
+                return;
+            }
+            parked.push(current.clone());
+        }
+        match timeout {
+            Some(timeout) => thread::park_timeout(timeout),
+            None => thread::park(),
+        }
+        // After a timeout or a spurious wakeup the entry is still there
+        let mut parked = self.parked.lock().unwrap();
+        if let Some(index) = parked.iter().position(|thread| thread.id() == current.id()) {
+            parked.swap_remove(index);
+        }
+    }
+
+    fn wake_all(&self, _word: &AtomicU32) {
+        let parked = std::mem::take(&mut *self.parked.lock().unwrap());
+        for thread in parked {
+            thread.unpark();
+        }
+    }
+}
+
+// Blocks on a condition variable, as WaitGroupImpl used to do
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct CondvarStrategy {
+    lock: Mutex<()>,
+    condition: Condvar,
+}
+
+#[cfg(feature = "std")]
+impl CondvarStrategy {
+    pub fn new() -> Self {
+        CondvarStrategy {
+            lock: Mutex::new(()),
+            condition: Condvar::new(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl WaitStrategy for CondvarStrategy {
+    fn wait(&self, word: &AtomicU32, expected: u32, timeout: Option<Duration>) {
+        let guard = self.lock.lock().unwrap();
+        if word.load(Ordering::SeqCst) != expected {
+            return;
+        }
+        match timeout {
+            Some(timeout) => drop(self.condition.wait_timeout(guard, timeout).unwrap()),
+            None => drop(self.condition.wait(guard).unwrap()),
+        }
+    }
+
+    fn wake_all(&self, _word: &AtomicU32) {
+        // Lock ensures that a waiter is either before the check or already sleeping
+        let _guard = self.lock.lock().unwrap();
+        self.condition.notify_all();
+    }
+}
+
+// Sleeps in the kernel on the word itself, no list of waiters is needed
+#[cfg(target_os = "linux")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FutexStrategy;
+
+#[cfg(target_os = "linux")]
+impl WaitStrategy for FutexStrategy {
+    fn wait(&self, word: &AtomicU32, expected: u32, timeout: Option<Duration>) {
+        // Durations which don't fit into timespec are treated as infinite
+        let timespec = timeout.and_then(|timeout| {
+            Some(libc::timespec {
+                tv_sec: libc::time_t::try_from(timeout.as_secs()).ok()?,
+                tv_nsec: timeout.subsec_nanos() as libc::c_long,
+            })
+        });
+        let timespec_ptr = match &timespec {
+            Some(timespec) => timespec as *const libc::timespec,
+            None => core::ptr::null(),
+        };
+        unsafe {
+            libc::syscall(
+                libc::SYS_futex,
+                word.as_ptr(),
+                libc::FUTEX_WAIT | libc::FUTEX_PRIVATE_FLAG,
+                expected,
+                timespec_ptr,
+            );
+        }
+    }
+
+    fn wake_all(&self, word: &AtomicU32) {
+        unsafe {
+            libc::syscall(
+                libc::SYS_futex,
+                word.as_ptr(),
+                libc::FUTEX_WAKE | libc::FUTEX_PRIVATE_FLAG,
+                i32::MAX,
+            );
+        }
+    }
+}
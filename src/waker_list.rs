@@ -0,0 +1,74 @@
+use alloc::vec::Vec;
+use core::mem;
+use core::task::Waker;
+
+enum Slot {
+    Vacant,
+    // Owned by a future, waker is None after the future was woken
+    Occupied(Option<Waker>),
+}
+
+// Wakers of pending futures. A future keeps its key across polls
+// and releases the slot on drop, only then the slot can be reused.
+pub struct WakerList {
+    slots: Vec<Slot>,
+}
+
+impl WakerList {
+    pub fn new() -> WakerList {
+        WakerList { slots: Vec::new() }
+    }
+
+    // Returns true if the future had no pending waker before
+    pub fn register(&mut self, key: &mut Option<usize>, waker: &Waker) -> bool {
+        let index = match *key {
+            Some(index) => index,
+            None => {
+                let index = self.vacant();
+                *key = Some(index);
+                index
+            }
+        };
+        match &mut self.slots[index] {
+            Slot::Occupied(Some(old)) if old.will_wake(waker) => false,
+            Slot::Occupied(Some(old)) => {
+                *old = waker.clone();
+                false
+            }
+            slot => {
+                *slot = Slot::Occupied(Some(waker.clone()));
+                true
+            }
+        }
+    }
+
+    // Returns true if the future had a pending waker
+    pub fn remove(&mut self, key: usize) -> bool {
+        let slot = mem::replace(&mut self.slots[key], Slot::Vacant);
+        matches!(slot, Slot::Occupied(Some(_)))
+    }
+
+    pub fn take_all(&mut self) -> Vec<Waker> {
+        self.slots
+            .iter_mut()
+            .filter_map(|slot| match slot {
+                Slot::Occupied(waker) => waker.take(),
+                Slot::Vacant => None,
+            })
+            .collect()
+    }
+
+    fn vacant(&mut self) -> usize {
+        match self
+            .slots
+            .iter()
+            .position(|slot| matches!(slot, Slot::Vacant))
+        {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot::Vacant);
+                self.slots.len() - 1
+            }
+        }
+    }
+}
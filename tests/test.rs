@@ -1,7 +1,22 @@
-use std::sync::atomic::{AtomicI32, Ordering};
+use futures_core::Stream;
+use std::error::Error;
+use std::future::{poll_fn, Future, IntoFuture};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicI32, AtomicU32, Ordering};
 use std::sync::Arc;
+#[cfg(feature = "std")]
+use std::sync::Barrier;
+use std::task::{Context, Poll, Wake, Waker};
 use std::thread;
-use wait_group::{GuardWaitGroup, ManualWaitGroup, SmartWaitGroup};
+use std::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+use wait_group::{
+    Case, GuardWaitGroup, ManualWaitGroup, MultiWaitGroup, SmartWaitGroup, SpinStrategy,
+    TaggedDoer, TaggedWaitGroup, TaggedWaiter, WaitGroupError, WaitStrategy, MAX_COUNTER,
+};
+#[cfg(feature = "std")]
+use wait_group::{CondvarStrategy, Order, ParkStrategy, SpinThenYieldStrategy};
 
 const ATTEMPTS: usize = 100; // number of attempts for searching deadlocks
 const THREADS_NUMBER: usize = 100;
@@ -9,6 +24,28 @@ const INITIAL_VALUE: i32 = 0;
 const EXPECTED_AFTER_WAITING: i32 = 100;
 const EXPECTED_AFTER_JOINING: i32 = -1;
 const INCORRECT_THREADS_NUMBER: usize = THREADS_NUMBER - 1;
+const TIMEOUT: Duration = Duration::from_millis(50);
+
+// Minimal executor for futures of wait groups, without any async runtime
+struct ThreadWaker(thread::Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
 
 #[test]
 fn wait_group() {
@@ -31,7 +68,7 @@ fn wait_group() {
             .collect::<Vec<_>>();
 
         // Wait until all N threads are finished
-        wg.wait();
+        wg.wait().unwrap();
         assert_eq!(counter.load(Ordering::SeqCst), EXPECTED_AFTER_WAITING);
         counter.store(EXPECTED_AFTER_JOINING, Ordering::SeqCst);
 
@@ -69,7 +106,7 @@ fn smart_wg() {
             .collect::<Vec<_>>();
 
         // Wait until all N threads are finished
-        waiter.wait();
+        waiter.wait().unwrap();
         assert_eq!(counter.load(Ordering::SeqCst), EXPECTED_AFTER_WAITING);
         counter.store(EXPECTED_AFTER_JOINING, Ordering::SeqCst);
 
@@ -102,7 +139,7 @@ fn manual_wg() {
             .collect::<Vec<_>>();
 
         // Wait until all N threads are finished
-        wg.wait();
+        wg.wait().unwrap();
         assert_eq!(counter.load(Ordering::SeqCst), EXPECTED_AFTER_WAITING);
         counter.store(EXPECTED_AFTER_JOINING, Ordering::SeqCst);
 
@@ -135,7 +172,7 @@ fn manual_wg_negative_counter() {
             .collect::<Vec<_>>();
 
         // Wait until all N threads are finished
-        wg.wait();
+        wg.wait().unwrap();
 
         // Assure threads are finished for avoiding false-positive result
         for handler in thread_handlers {
@@ -146,3 +183,1347 @@ fn manual_wg_negative_counter() {
         assert_eq!(counter.load(Ordering::SeqCst), EXPECTED_AFTER_JOINING);
     }
 }
+
+#[test]
+fn smart_wg_many_waiters() {
+    for _ in 0..ATTEMPTS {
+        let counter = Arc::new(AtomicI32::new(INITIAL_VALUE));
+
+        let wg = SmartWaitGroup::new();
+        let doer = wg.doer();
+
+        // Spawn N waiters which check the counter after waiting
+        let waiter_handlers = (0..THREADS_NUMBER)
+            .map(|_| {
+                let waiter = wg.waiter();
+                let counter = Arc::clone(&counter);
+                thread::spawn(move || {
+                    waiter.wait().unwrap();
+                    assert_eq!(counter.load(Ordering::SeqCst), EXPECTED_AFTER_WAITING);
+                })
+            })
+            .collect::<Vec<_>>();
+
+        counter.store(EXPECTED_AFTER_WAITING, Ordering::SeqCst);
+        drop(doer);
+
+        // Every waiter must be released after the last doer is gone
+        for handler in waiter_handlers {
+            handler.join().unwrap();
+        }
+        assert_eq!(wg.counter(), 0);
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn manual_wg_wait_timeout() {
+    let wg = ManualWaitGroup::new();
+    wg.add(2);
+
+    // Counter is never drained, so waiting must expire and report the counter
+    let start = Instant::now();
+    match wg.wait_timeout(TIMEOUT) {
+        Err(WaitGroupError::Timeout { counter, .. }) => assert_eq!(counter, 2),
+        other => panic!("Unexpected result: {:?}", other),
+    }
+    assert!(start.elapsed() >= TIMEOUT);
+
+    let handler = {
+        let wg = wg.clone();
+        thread::spawn(move || {
+            wg.done();
+            wg.done();
+        })
+    };
+    wg.wait_deadline(Instant::now() + Duration::from_secs(10))
+        .unwrap();
+    handler.join().unwrap();
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn smart_wg_switch_timeout() {
+    let first = SmartWaitGroup::new();
+    let second = SmartWaitGroup::new();
+    let second_doer = second.doer();
+
+    // Doer of the first group must be released after timeout
+    assert!(first.switch_do_wait_timeout(&second, TIMEOUT).is_err());
+    assert_eq!(first.counter(), 0);
+    assert!(first.switch_unique_timeout(&second, TIMEOUT).is_err());
+    assert_eq!(first.counter(), 0);
+    assert!(first.waiter().wait_timeout(TIMEOUT).is_ok());
+
+    drop(second_doer);
+    let doer = first.switch_wait_do_timeout(&second, TIMEOUT).unwrap();
+    assert_eq!(first.counter(), 1);
+    drop(doer);
+
+    // Poison of the second group is reported to its own waiters only,
+    // the same as for untimed switches
+    let second_doer = second.doer();
+    let _ = thread::spawn(move || {
+        let _doer = second_doer;
+        panic!("second doer");
+    })
+    .join();
+    let doer = first.switch_do_wait_timeout(&second, TIMEOUT).unwrap();
+    drop(doer);
+    let doer = first.switch_do_wait(&second);
+    drop(doer);
+    assert!(second.waiter().wait().is_err());
+}
+
+#[test]
+fn smart_wg_try_wait() {
+    for _ in 0..ATTEMPTS {
+        let counter = Arc::new(AtomicI32::new(INITIAL_VALUE));
+        let (waiter, doer) = SmartWaitGroup::splitted();
+
+        let thread_handlers = (0..THREADS_NUMBER)
+            .map(|_| {
+                let doer = doer.clone();
+                let counter = Arc::clone(&counter);
+                thread::spawn(move || {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                    drop(doer)
+                })
+            })
+            .collect::<Vec<_>>();
+        assert!(!waiter.try_wait());
+        drop(doer);
+
+        // Poll without blocking, relaxed writes of doers must be visible after success
+        while !waiter.try_wait() {
+            thread::yield_now();
+        }
+        assert_eq!(counter.load(Ordering::Relaxed), EXPECTED_AFTER_WAITING);
+
+        for handler in thread_handlers {
+            handler.join().unwrap();
+        }
+    }
+}
+
+#[test]
+fn smart_wg_async() {
+    for _ in 0..ATTEMPTS {
+        let counter = Arc::new(AtomicI32::new(INITIAL_VALUE));
+        let (waiter, doer) = SmartWaitGroup::splitted();
+
+        // Await and wait for the same group simultaneously
+        let async_waiter = {
+            let waiter = waiter.clone();
+            let counter = Arc::clone(&counter);
+            thread::spawn(move || {
+                block_on(waiter.into_future()).unwrap();
+                assert_eq!(counter.load(Ordering::SeqCst), EXPECTED_AFTER_WAITING);
+            })
+        };
+        let thread_handlers = (0..THREADS_NUMBER)
+            .map(|_| {
+                let doer = doer.clone();
+                let counter = Arc::clone(&counter);
+                thread::spawn(move || {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    drop(doer)
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(doer);
+
+        waiter.wait().unwrap();
+        assert_eq!(counter.load(Ordering::SeqCst), EXPECTED_AFTER_WAITING);
+        async_waiter.join().unwrap();
+        for handler in thread_handlers {
+            handler.join().unwrap();
+        }
+    }
+}
+
+#[test]
+fn manual_wg_async_dropped() {
+    let wg = ManualWaitGroup::new();
+    wg.add(1);
+
+    // Future is dropped while pending, completion must not touch its waker
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = wg.wait_async();
+    assert!(Pin::new(&mut future).poll(&mut cx).is_pending());
+    drop(future);
+
+    let mut future = wg.wait_async();
+    assert!(Pin::new(&mut future).poll(&mut cx).is_pending());
+    wg.done();
+    assert!(Pin::new(&mut future).poll(&mut cx).is_ready());
+    block_on(wg.wait_async()).unwrap();
+}
+
+// Panics are detected with std only
+#[cfg(feature = "std")]
+#[test]
+fn smart_wg_poisoned() {
+    let (waiter, doer) = SmartWaitGroup::splitted();
+
+    // Every second thread panics while holding its doer
+    let thread_handlers = (0..THREADS_NUMBER)
+        .map(|i| {
+            let doer = doer.clone();
+            thread::spawn(move || {
+                let _doer = doer;
+                if i % 2 == 0 {
+                    panic!("Doer panicked");
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    drop(doer);
+
+    match waiter.wait() {
+        Err(WaitGroupError::Poisoned { panicked, .. }) => assert_eq!(panicked, THREADS_NUMBER / 2),
+        other => panic!("Unexpected result: {:?}", other),
+    }
+    assert!(waiter.is_poisoned());
+    for handler in thread_handlers {
+        let _ = handler.join();
+    }
+
+    waiter.clear_poison();
+    waiter.wait().unwrap();
+}
+
+// Panics are detected with std only
+#[cfg(feature = "std")]
+#[test]
+fn guard_wg_poisoned() {
+    let wg = GuardWaitGroup::new();
+    let handler = {
+        let wg = wg.clone();
+        thread::spawn(move || {
+            let _wg = wg;
+            panic!("Guard panicked");
+        })
+    };
+    assert!(handler.join().is_err());
+    assert!(wg.wait().is_err());
+    wg.clear_poison();
+    wg.wait().unwrap();
+}
+
+#[test]
+fn guard_wg_drop_subscribed() {
+    let wg = GuardWaitGroup::new();
+    let mut subscription = wg.subscribe();
+    let clone = wg.clone();
+    assert_eq!(subscription.next(), Some(1));
+
+    // Subscription shares the group, yet clones are still released and the root isn't
+    drop(clone);
+    assert_eq!(subscription.next(), Some(0));
+    let observer = wg.clone();
+    drop(wg);
+    assert_eq!(observer.counter(), 1);
+    drop(observer);
+    assert_eq!(subscription.next(), Some(0));
+
+    let wg = GuardWaitGroup::new();
+    let _subscription = wg.subscribe();
+    drop(wg);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn smart_wg_cancel() {
+    let counter = Arc::new(AtomicI32::new(INITIAL_VALUE));
+    let wg = SmartWaitGroup::new();
+
+    // Workers poll the flag or sleep until cancellation
+    let thread_handlers = (0..THREADS_NUMBER)
+        .map(|i| {
+            let doer = wg.doer();
+            let counter = Arc::clone(&counter);
+            thread::spawn(move || {
+                if i % 2 == 0 {
+                    while !doer.is_cancelled() {
+                        thread::yield_now();
+                    }
+                } else {
+                    doer.wait_cancelled();
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+        })
+        .collect::<Vec<_>>();
+
+    assert!(wg.waiter().wait_timeout(TIMEOUT).is_err());
+    wg.cancel();
+    assert!(wg.is_cancelled());
+    wg.waiter().wait().unwrap();
+    assert_eq!(counter.load(Ordering::SeqCst), EXPECTED_AFTER_WAITING);
+
+    for handler in thread_handlers {
+        handler.join().unwrap();
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn manual_wg_wait_cancelled_timeout() {
+    let wg = ManualWaitGroup::new();
+    assert!(!wg.wait_cancelled_timeout(TIMEOUT));
+    wg.cancel();
+    assert!(wg.wait_cancelled_timeout(TIMEOUT));
+}
+
+#[test]
+fn smart_wg_failed_doers() {
+    let (waiter, doer) = SmartWaitGroup::splitted();
+
+    let thread_handlers = (0..THREADS_NUMBER)
+        .map(|i| {
+            let doer = doer.clone();
+            thread::spawn(move || {
+                if i % 10 == 0 {
+                    doer.fail(format!("Task {} failed", i));
+                } else {
+                    doer.finish(Ok::<(), String>(()));
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    drop(doer);
+
+    match waiter.wait() {
+        Err(WaitGroupError::Failed { error, .. }) => assert!(error.to_string().starts_with("Task")),
+        other => panic!("Unexpected result: {:?}", other),
+    }
+    assert_eq!(waiter.errors().len(), THREADS_NUMBER / 10);
+    for handler in thread_handlers {
+        handler.join().unwrap();
+    }
+
+    waiter.clear_errors();
+    waiter.wait().unwrap();
+}
+
+#[test]
+fn smart_wg_collecting() {
+    for _ in 0..ATTEMPTS {
+        let (waiter, doer) = SmartWaitGroup::<usize>::collecting().split();
+
+        // Scatter the work and gather the results
+        let thread_handlers = (0..THREADS_NUMBER)
+            .map(|i| {
+                let doer = doer.clone();
+                thread::spawn(move || doer.complete(i * 2))
+            })
+            .collect::<Vec<_>>();
+        drop(doer);
+
+        let mut results = waiter.wait().unwrap();
+        results.sort_unstable();
+        assert_eq!(
+            results,
+            (0..THREADS_NUMBER).map(|i| i * 2).collect::<Vec<_>>()
+        );
+        // Results are handed over only once
+        assert!(waiter.wait().unwrap().is_empty());
+
+        for handler in thread_handlers {
+            handler.join().unwrap();
+        }
+    }
+}
+
+#[test]
+fn smart_wg_failed_batch_drained() {
+    let wg = SmartWaitGroup::<usize>::collecting();
+    let waiter = wg.waiter();
+    wg.doer().complete(1);
+    wg.doer().fail("Task failed");
+    assert!(waiter.wait().is_err());
+
+    // Values of the failed batch don't leak into the next one
+    waiter.clear_errors();
+    wg.doer().complete(2);
+    assert_eq!(waiter.wait().unwrap(), vec![2]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn manual_wg_reused_batches() {
+    for _ in 0..ATTEMPTS {
+        let wg = ManualWaitGroup::new();
+        wg.add(1);
+        let generation = wg.generation();
+
+        let waiter_handlers = (0..THREADS_NUMBER / 10)
+            .map(|_| {
+                let wg = wg.clone();
+                thread::spawn(move || wg.wait_timeout(Duration::from_secs(10)).unwrap())
+            })
+            .collect::<Vec<_>>();
+        thread::sleep(Duration::from_millis(10));
+
+        // Counter is raised again by the next batch right after it has dropped to zero
+        wg.done();
+        wg.add(1);
+
+        // Waiters blocked at the moment of zero must be released for the first batch
+        for handler in waiter_handlers {
+            assert_eq!(handler.join().unwrap().value(), generation.value() + 1);
+        }
+        wg.done();
+        assert_eq!(wg.wait().unwrap().value(), generation.value() + 2);
+    }
+}
+
+#[cfg(feature = "std")]
+fn manual_wg_with(wg: ManualWaitGroup) {
+    let counter = Arc::new(AtomicI32::new(INITIAL_VALUE));
+    let thread_handlers = (0..THREADS_NUMBER)
+        .map(|_| {
+            let wg = wg.clone();
+            wg.add(1);
+            let counter = Arc::clone(&counter);
+            thread::spawn(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                wg.done();
+            })
+        })
+        .collect::<Vec<_>>();
+
+    wg.wait().unwrap();
+    assert_eq!(counter.load(Ordering::SeqCst), EXPECTED_AFTER_WAITING);
+    assert!(wg.wait_timeout(TIMEOUT).is_ok());
+    for handler in thread_handlers {
+        handler.join().unwrap();
+    }
+
+    // Timed out wait must return for every strategy
+    wg.add(1);
+    assert!(wg.wait_timeout(TIMEOUT).is_err());
+    wg.done();
+}
+
+// Spinning is the fallback strategy without std
+#[cfg(not(feature = "std"))]
+#[test]
+fn manual_wg_no_std() {
+    let wg = ManualWaitGroup::with_strategy(SpinStrategy);
+    let counter = Arc::new(AtomicI32::new(INITIAL_VALUE));
+    let thread_handlers = (0..THREADS_NUMBER)
+        .map(|_| {
+            let wg = wg.clone();
+            wg.add(1);
+            let counter = Arc::clone(&counter);
+            thread::spawn(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                wg.done();
+            })
+        })
+        .collect::<Vec<_>>();
+
+    wg.wait().unwrap();
+    assert!(wg.try_wait());
+    assert_eq!(counter.load(Ordering::SeqCst), EXPECTED_AFTER_WAITING);
+    for handler in thread_handlers {
+        handler.join().unwrap();
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn manual_wg_strategies() {
+    manual_wg_with(ManualWaitGroup::with_strategy(SpinStrategy));
+    manual_wg_with(ManualWaitGroup::with_strategy(
+        SpinThenYieldStrategy::default(),
+    ));
+    manual_wg_with(ManualWaitGroup::with_strategy(ParkStrategy::new()));
+    manual_wg_with(ManualWaitGroup::with_strategy(CondvarStrategy::new()));
+}
+
+// User-provided blocking hook which yields and counts waits and wake ups
+#[derive(Default)]
+struct CountingStrategy {
+    waits: Arc<AtomicI32>,
+    wakes: Arc<AtomicI32>,
+}
+
+impl WaitStrategy for CountingStrategy {
+    fn wait(&self, word: &AtomicU32, expected: u32, _timeout: Option<Duration>) {
+        self.waits.fetch_add(1, Ordering::SeqCst);
+        while word.load(Ordering::SeqCst) == expected {
+            thread::yield_now();
+        }
+    }
+
+    fn wake_all(&self, _word: &AtomicU32) {
+        self.wakes.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn smart_wg_custom_strategy() {
+    let strategy = CountingStrategy::default();
+    let waits = Arc::clone(&strategy.waits);
+    let wakes = Arc::clone(&strategy.wakes);
+    let (waiter, doer) = SmartWaitGroup::with_strategy(strategy).split();
+
+    // Waker is called only if the waiter is already asleep
+    let handler = thread::spawn(move || waiter.wait().unwrap());
+    while waits.load(Ordering::SeqCst) == 0 {
+        thread::yield_now();
+    }
+    drop(doer);
+    handler.join().unwrap();
+    assert_eq!(wakes.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn manual_wg_counter_overflow() {
+    let wg = ManualWaitGroup::new();
+    match wg.try_add(isize::MAX) {
+        Err(WaitGroupError::CounterOverflow { counter, delta, .. }) => {
+            assert_eq!((counter, delta), (0, isize::MAX))
+        }
+        res => panic!("Unexpected result: {:?}", res),
+    }
+    assert!(!wg.add_if_empty(usize::MAX));
+    match wg.try_add_if_empty(MAX_COUNTER + 1) {
+        Err(WaitGroupError::CounterOverflow { counter, delta, .. }) => {
+            assert_eq!((counter, delta), (0, MAX_COUNTER as isize + 1))
+        }
+        res => panic!("Unexpected result: {:?}", res),
+    }
+    assert!(wg.try_add_if_empty(MAX_COUNTER).unwrap());
+    assert!(!wg.try_add_if_empty(1).unwrap());
+    wg.add(-(MAX_COUNTER as isize));
+
+    let half = (MAX_COUNTER / 2) as isize;
+    wg.try_add(half).unwrap();
+    wg.try_add(half).unwrap();
+    wg.try_add(1).unwrap();
+    assert_eq!(wg.counter(), MAX_COUNTER);
+    match wg.try_add(1) {
+        Err(WaitGroupError::CounterOverflow { counter, delta, .. }) => {
+            assert_eq!((counter, delta), (MAX_COUNTER, 1))
+        }
+        res => panic!("Unexpected result: {:?}", res),
+    }
+    assert_eq!(wg.counter(), MAX_COUNTER);
+
+    wg.try_add(-half).unwrap();
+    wg.try_add(-half - 1).unwrap();
+    assert!(wg.try_wait());
+    wg.wait().unwrap();
+
+    match wg.try_add(isize::MIN) {
+        Err(WaitGroupError::NegativeCounter { counter, delta, .. }) => {
+            assert_eq!((counter, delta), (0, isize::MIN))
+        }
+        res => panic!("Unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn smart_wg_named_errors() {
+    let wg = SmartWaitGroup::new().named("db");
+    assert_eq!(wg.name().as_deref(), Some("db"));
+    let waiter = wg.waiter();
+    let doer = wg.doer();
+
+    wg.cancel();
+    match doer.check_cancelled() {
+        Err(error @ WaitGroupError::Cancelled { .. }) => {
+            assert_eq!(error.group(), Some("db"));
+            assert_eq!(error.to_string(), "wait group `db`: cancelled");
+        }
+        res => panic!("Unexpected result: {:?}", res),
+    }
+
+    wg.close();
+    assert!(wg.unique_doer().is_none());
+    match wg.try_doer() {
+        Err(WaitGroupError::Closed { group }) => assert_eq!(group.as_deref(), Some("db")),
+        res => panic!("Unexpected result: {:?}", res.map(|_| ())),
+    }
+    // Clones of admitted doers are still counted in a closed group
+    drop(doer.clone());
+    assert_eq!(wg.counter(), 1);
+
+    doer.fail("Task 1 failed");
+    let error: Box<dyn Error> = Box::new(waiter.wait().unwrap_err());
+    assert_eq!(
+        error.to_string(),
+        "wait group `db`: doer failed: Task 1 failed"
+    );
+    assert_eq!(error.source().unwrap().to_string(), "Task 1 failed");
+
+    let wg = GuardWaitGroup::new();
+    wg.close();
+    drop(wg.clone());
+    assert!(wg.clone_unique().is_none());
+    assert_eq!(wg.counter(), 0);
+
+    let wg = ManualWaitGroup::new();
+    let error = wg.try_add(-2).unwrap_err();
+    assert_eq!(error.group(), None);
+    assert_eq!(
+        error.to_string(),
+        "wait group: counter 0 becomes negative after adding -2"
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn manual_wg_wait_until_at_most() {
+    let wg = ManualWaitGroup::new();
+    wg.add(10);
+
+    // Each waiter is released as soon as its own threshold is crossed
+    let waiter_handlers = [7, 3]
+        .iter()
+        .map(|&threshold| {
+            let wg = wg.clone();
+            thread::spawn(move || {
+                let counter = wg.wait_until_at_most(threshold).unwrap();
+                assert!(counter <= threshold);
+                wg.counter()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    thread::sleep(TIMEOUT);
+    for _ in 0..3 {
+        wg.done();
+    }
+    thread::sleep(TIMEOUT);
+    assert!(!waiter_handlers[1].is_finished());
+    for _ in 0..4 {
+        wg.done();
+    }
+
+    let counters = waiter_handlers
+        .into_iter()
+        .map(|handler| handler.join().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(counters, vec![7, 3]);
+
+    match wg.wait_until_at_most_timeout(2, TIMEOUT) {
+        Err(WaitGroupError::Timeout { counter, .. }) => assert_eq!(counter, 3),
+        res => panic!("Unexpected result: {:?}", res),
+    }
+    assert_eq!(wg.wait_until_at_most(3).unwrap(), 3);
+}
+
+#[test]
+fn smart_wg_waiter_at_most() {
+    let (waiter, doer) = SmartWaitGroup::splitted();
+    let doers = (0..3).map(|_| doer.clone()).collect::<Vec<_>>();
+
+    let handler = thread::spawn(move || waiter.wait_until_at_most(1));
+    thread::sleep(TIMEOUT);
+    drop(doers);
+    assert_eq!(handler.join().unwrap().unwrap(), 1);
+    drop(doer);
+}
+
+#[test]
+fn manual_wg_subscribe() {
+    let wg = ManualWaitGroup::new();
+    let mut subscription = wg.subscribe();
+    assert_eq!(subscription.next(), Some(0));
+
+    wg.add(3);
+    assert_eq!(subscription.next(), Some(3));
+
+    // Slow subscriber sees only the latest counter
+    wg.done();
+    wg.done();
+    assert_eq!(subscription.next(), Some(1));
+
+    let handler = {
+        let wg = wg.clone();
+        thread::spawn(move || {
+            thread::sleep(TIMEOUT);
+            wg.done();
+        })
+    };
+    assert_eq!(subscription.next(), Some(0));
+    handler.join().unwrap();
+}
+
+#[test]
+fn smart_wg_subscribe_stream() {
+    let wg = SmartWaitGroup::new();
+    let mut subscription = wg.subscribe();
+    let mut next = || block_on(poll_fn(|cx| Pin::new(&mut subscription).poll_next(cx)));
+    assert_eq!(next(), Some(0));
+
+    let doer = wg.doer();
+    let handler = thread::spawn(move || {
+        let doers = (0..3).map(|_| doer.clone()).collect::<Vec<_>>();
+        thread::sleep(TIMEOUT);
+        drop(doers);
+        thread::sleep(TIMEOUT);
+        drop(doer);
+    });
+
+    // Counters are yielded until the group is drained, some of them coalesced
+    let mut counters = Vec::new();
+    while counters.last() != Some(&0) {
+        counters.push(next().unwrap());
+    }
+    handler.join().unwrap();
+    assert!(counters.windows(2).all(|pair| pair[0] != pair[1]));
+    assert!(counters.contains(&1));
+}
+
+#[test]
+fn smart_wg_on_complete() {
+    let wg = SmartWaitGroup::new();
+    let doer = wg.doer();
+    let calls = Arc::new(AtomicI32::new(0));
+    let (sender, receiver) = std::sync::mpsc::channel();
+    {
+        let calls = Arc::clone(&calls);
+        wg.on_complete(move |res| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            sender.send((thread::current().id(), res.unwrap())).unwrap();
+        });
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+    // Callback is run by the thread releasing the last doer
+    let handler = thread::spawn(move || {
+        drop(doer);
+        thread::current().id()
+    });
+    let releaser = handler.join().unwrap();
+    let (runner, generation) = receiver.recv().unwrap();
+    assert_eq!(runner, releaser);
+    assert_eq!(generation, wg.generation());
+
+    // Exactly once, even though the group is drained again
+    drop(wg.doer());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn manual_wg_on_complete_empty() {
+    let wg = ManualWaitGroup::new();
+    wg.add(1);
+    wg.done();
+    wg.add(1);
+    wg.try_add(-1).unwrap();
+    let generation = wg.generation();
+
+    // Group is already empty, so the callback is run at once by the caller
+    let caller = thread::current().id();
+    let (sender, receiver) = std::sync::mpsc::channel();
+    wg.on_complete(move |res| {
+        assert_eq!(thread::current().id(), caller);
+        sender.send(res.unwrap()).unwrap();
+    });
+    assert_eq!(receiver.try_recv().unwrap(), generation);
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn smart_wg_stats() {
+    let strategy = CountingStrategy::default();
+    let waits = Arc::clone(&strategy.waits);
+    let wg = SmartWaitGroup::with_strategy(strategy);
+    let waiter = wg.waiter();
+    let doers = (0..3).map(|_| wg.doer()).collect::<Vec<_>>();
+    drop(wg.doer());
+
+    let handler = thread::spawn(move || {
+        let started = Instant::now();
+        waiter.wait().unwrap();
+        started.elapsed()
+    });
+    // Doers are released only once the waiter is blocked
+    while waits.load(Ordering::SeqCst) == 0 {
+        thread::yield_now();
+    }
+    thread::sleep(TIMEOUT);
+    drop(doers);
+    let elapsed = handler.join().unwrap();
+
+    let stats = wg.stats();
+    assert_eq!(stats.issued, 4);
+    assert_eq!(stats.peak, 4);
+    assert_eq!(stats.wait_latency.count, 1);
+    assert!(stats.wait_latency.max >= TIMEOUT);
+    assert!(stats.wait_latency.max <= elapsed);
+    assert_eq!(stats.doer_lifetime.count, 4);
+    assert_eq!(stats.doer_lifetime.buckets.iter().sum::<u64>(), 4);
+    assert!(stats.doer_lifetime.mean().unwrap() <= stats.doer_lifetime.max);
+}
+
+// Records names of spans and events messages
+#[cfg(feature = "tracing")]
+#[derive(Clone, Default)]
+struct TraceRecorder {
+    spans: Arc<std::sync::Mutex<Vec<&'static str>>>,
+    events: Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+#[cfg(feature = "tracing")]
+impl tracing::Subscriber for TraceRecorder {
+    fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(span.metadata().name());
+        tracing::span::Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        struct Message(String);
+        impl tracing::field::Visit for Message {
+            fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                if field.name() == "message" {
+                    self.0 = format!("{:?}", value);
+                }
+            }
+        }
+        let mut message = Message(String::new());
+        event.record(&mut message);
+        self.events.lock().unwrap().push(message.0);
+    }
+
+    fn enter(&self, _span: &tracing::span::Id) {}
+
+    fn exit(&self, _span: &tracing::span::Id) {}
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn smart_wg_tracing() {
+    let recorder = TraceRecorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let (waiter, doer) = SmartWaitGroup::new().named("db").split();
+        drop(doer);
+        waiter.wait().unwrap();
+
+        let wg = ManualWaitGroup::new();
+        wg.add(1);
+        assert!(wg.try_done().is_ok());
+        assert!(wg.try_done().is_err());
+    });
+
+    assert_eq!(*recorder.spans.lock().unwrap(), vec!["doer", "wait"]);
+    assert_eq!(
+        *recorder.events.lock().unwrap(),
+        vec![
+            "doer added",
+            "doer released",
+            "waiting",
+            "released",
+            "counter changed",
+            "counter changed",
+            "counter not changed",
+        ]
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn smart_wg_outstanding() {
+    let wg = SmartWaitGroup::new().named("db").track_doers(true);
+    let waiter = wg.waiter();
+    let first = wg.doer().labeled("first");
+    let second = first.clone();
+    let third = thread::Builder::new()
+        .name("worker".to_string())
+        .spawn({
+            let wg = wg.clone();
+            move || wg.doer()
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    drop(second);
+
+    let outstanding = wg.outstanding();
+    assert_eq!(outstanding.len(), 2);
+    assert_eq!(outstanding[0].label(), Some("first"));
+    assert_eq!(outstanding[1].label(), None);
+    assert_eq!(outstanding[1].thread(), Some("worker"));
+    assert!(outstanding[1].backtrace().is_some());
+
+    match waiter.wait_timeout(TIMEOUT) {
+        Err(error @ WaitGroupError::Timeout { .. }) => {
+            let thread = outstanding[0].thread().unwrap();
+            assert_eq!(
+                error.to_string(),
+                format!(
+                    "wait group `db`: timed out with counter 2, outstanding doers: \
+                     #0 `first` created on thread `{}`, #2 created on thread `worker`",
+                    thread
+                )
+            );
+        }
+        res => panic!("Unexpected result: {:?}", res),
+    }
+
+    drop(first);
+    drop(third);
+    assert!(waiter.outstanding().is_empty());
+    waiter.wait().unwrap();
+}
+
+#[test]
+fn guard_wg_outstanding() {
+    let wg = GuardWaitGroup::new().track_doers(false);
+    let doer = wg.clone().labeled("guard");
+    let clone = doer.clone();
+    let labels = wg
+        .outstanding()
+        .iter()
+        .map(|info| info.label().map(String::from))
+        .collect::<Vec<_>>();
+    assert_eq!(labels, vec![Some("guard".to_string()); 2]);
+    #[cfg(feature = "std")]
+    assert!(wg.outstanding()[0].backtrace().is_none());
+
+    drop(doer);
+    drop(clone);
+    assert!(wg.outstanding().is_empty());
+
+    // Root guard isn't registered, but its label is still inherited
+    let root = GuardWaitGroup::new().track_doers(false).labeled("root");
+    let clone = root.clone();
+    let labels = root
+        .outstanding()
+        .iter()
+        .map(|info| info.label().map(String::from))
+        .collect::<Vec<_>>();
+    assert_eq!(labels, vec![Some("root".to_string())]);
+    drop(clone);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn smart_wg_deadlock() {
+    let first = SmartWaitGroup::new().named("first").detect_deadlocks();
+    let second = SmartWaitGroup::new().named("second").detect_deadlocks();
+    let barrier = Arc::new(Barrier::new(2));
+
+    // Holds a doer of the second group and waits for the first one
+    let handler = {
+        let (first, second, barrier) = (first.clone(), second.clone(), Arc::clone(&barrier));
+        thread::spawn(move || {
+            let doer = second.doer().held();
+            barrier.wait();
+            let res = first.waiter().wait();
+            drop(doer);
+            res.map(|_| ())
+        })
+    };
+
+    // Holds a doer of the first group and waits for the second one
+    let doer = first.doer().held();
+    barrier.wait();
+    let res = second.waiter().wait();
+    drop(doer);
+
+    // Whichever thread closes the cycle gets the error, the other one is released
+    match (res, handler.join().unwrap()) {
+        (Ok(_), Err(error @ WaitGroupError::Deadlock { .. })) => assert_eq!(
+            error.to_string(),
+            "wait group `first`: deadlock on groups `first` -> `second`"
+        ),
+        (Err(error @ WaitGroupError::Deadlock { .. }), Ok(())) => assert_eq!(
+            error.to_string(),
+            "wait group `second`: deadlock on groups `second` -> `first`"
+        ),
+        other => panic!("Unexpected result: {:?}", other),
+    }
+    first.waiter().wait().unwrap();
+    second.waiter().wait().unwrap();
+
+    // Waiting for a group the thread holds itself is a cycle too
+    let doer = second.doer().held();
+    match first.try_switch_do_wait(&second) {
+        Err(error @ WaitGroupError::Deadlock { .. }) => assert_eq!(
+            error.to_string(),
+            "wait group `second`: deadlock on groups `second`"
+        ),
+        res => panic!("Unexpected result: {:?}", res.map(|_| ())),
+    }
+    assert_eq!(first.counter(), 0);
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        first.switch_do_wait(&second)
+    }));
+    assert!(res.is_err());
+    assert_eq!(first.counter(), 0);
+    drop(doer);
+    first.waiter().wait().unwrap();
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn smart_wg_deadlock_detached() {
+    let first = SmartWaitGroup::new().named("first").detect_deadlocks();
+    let second = SmartWaitGroup::new().named("second").detect_deadlocks();
+    let empty = SmartWaitGroup::new();
+    let barrier = Arc::new(Barrier::new(2));
+
+    // Holds a doer of the second group and waits for the first one
+    let handler = {
+        let (first, second, barrier) = (first.clone(), second.clone(), Arc::clone(&barrier));
+        thread::spawn(move || {
+            let doer = second.doer().held();
+            barrier.wait();
+            let res = first.waiter().wait();
+            drop(doer);
+            res.map(|_| ())
+        })
+    };
+
+    // Switched doer of the first group is handed to a worker, so waiting
+    // for the second group doesn't close a cycle
+    let doer = first.switch_wait_do(&empty).detached();
+    let worker = thread::spawn(move || {
+        thread::sleep(TIMEOUT);
+        drop(doer);
+    });
+    barrier.wait();
+    second.waiter().wait().unwrap();
+    handler.join().unwrap().unwrap();
+    worker.join().unwrap();
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn smart_wg_levels() {
+    let high = SmartWaitGroup::new().named("high").leveled(2);
+    let low = SmartWaitGroup::new().named("low").leveled(1);
+
+    // Safe order is picked by levels
+    let doer = high.switch_by_level(&low).unwrap();
+    assert_eq!(high.counter(), 1);
+    drop(doer);
+    let doer = low.switch_by_level(&high).unwrap();
+    drop(doer);
+
+    // Order asked for is replaced with the safe one
+    let doer = low.switch(&high, Order::DoerWaiter);
+    drop(doer);
+
+    // Holding a doer of the low group while waiting for it again is rejected
+    let held = low.doer().held();
+    match high.try_switch(&low, Order::WaiterDoer) {
+        Err(error @ WaitGroupError::LevelViolation { .. }) => assert_eq!(
+            error.to_string(),
+            "wait group `low`: waiting for level 1 while holding a doer of level 1"
+        ),
+        res => panic!("Unexpected result: {:?}", res.map(|_| ())),
+    }
+    assert_eq!(high.counter(), 0);
+    drop(held);
+
+    // Hierarchy is checked for every wait of the thread holding a switched doer
+    let doer = high.switch_do_wait(&low);
+    let same = SmartWaitGroup::new().leveled(2);
+    match same.waiter().wait() {
+        Err(WaitGroupError::LevelViolation { level, held, .. }) => {
+            assert_eq!((level, held), (2, 2))
+        }
+        res => panic!("Unexpected result: {:?}", res),
+    }
+    drop(doer);
+    same.waiter().wait().unwrap();
+
+    // Doer handed to another thread isn't held by the switching one
+    let doer = high.switch_do_wait(&low).detached();
+    let worker = thread::spawn(move || {
+        thread::sleep(TIMEOUT);
+        drop(doer);
+    });
+    same.waiter().wait().unwrap();
+    worker.join().unwrap();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Task {
+    Normal,
+    Special,
+}
+
+impl Case for Task {
+    const COUNT: usize = 2;
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[test]
+fn multi_wg() {
+    let wg = MultiWaitGroup::<Task>::new();
+    let special = wg.doer(Task::Special);
+    assert_eq!(special.case(), Task::Special);
+    assert_eq!(
+        (wg.counter(Task::Normal), wg.counter(Task::Special)),
+        (0, 1)
+    );
+    assert!(wg.unique_doer(Task::Special).is_none());
+
+    // Normal doer is given only after special ones are done
+    let handler = {
+        let wg = wg.clone();
+        thread::spawn(move || wg.switch_wait_do(Task::Normal, Task::Special))
+    };
+    thread::sleep(TIMEOUT);
+    assert_eq!(wg.counter(Task::Normal), 0);
+    drop(special);
+    let normal = handler.join().unwrap();
+    assert_eq!(normal.case(), Task::Normal);
+
+    let waiter = wg.waiter(Task::Normal);
+    assert!(!waiter.try_wait());
+    drop(normal);
+    waiter.wait().unwrap();
+
+    let special = wg.switch_unique(Task::Special, Task::Normal).unwrap();
+    assert!(wg.switch_unique(Task::Special, Task::Normal).is_none());
+    drop(special);
+    assert_eq!(
+        (wg.counter(Task::Normal), wg.counter(Task::Special)),
+        (0, 0)
+    );
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn multi_wg_derive() {
+    #[derive(Clone, Copy, Case)]
+    enum Stage {
+        Load,
+        Parse,
+        Store,
+    }
+
+    assert_eq!(Stage::COUNT, 3);
+    assert_eq!(
+        [Stage::Load, Stage::Parse, Stage::Store].map(Case::index),
+        [0, 1, 2]
+    );
+    let wg = MultiWaitGroup::<Stage>::new();
+    let doer = wg.switch_do_wait(Stage::Store, Stage::Parse);
+    assert_eq!(wg.counter(Stage::Store), 1);
+    drop(doer);
+    wg.waiter(Stage::Load).wait().unwrap();
+}
+
+struct Normal;
+struct Special;
+
+fn normal_task(counter: &AtomicI32, _doer: TaggedDoer<Normal>) {
+    counter.fetch_add(1, Ordering::SeqCst);
+}
+
+#[test]
+fn tagged_wg() {
+    let counter = AtomicI32::new(INITIAL_VALUE);
+    let normal_wg = TaggedWaitGroup::<Normal>::new();
+    let special_wg = SmartWaitGroup::new().tagged::<Special>();
+
+    // normal_task(&counter, special_wg.doer()) doesn't compile, see the TaggedDoer doctest
+    normal_task(&counter, normal_wg.switch_wait_do(&special_wg));
+    let special = special_wg.switch_unique(&normal_wg).unwrap();
+    assert!(!special.is_cancelled());
+    let waiter: TaggedWaiter<Special> = special_wg.waiter();
+    assert!(!waiter.try_wait());
+    drop(special);
+    waiter.wait().unwrap();
+    assert_eq!(counter.load(Ordering::SeqCst), 1);
+
+    let (waiter, doer) = TaggedWaitGroup::<Normal, i32>::collecting().split();
+    doer.complete(7);
+    assert_eq!(waiter.wait().unwrap(), vec![7]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn smart_wg_spawn() {
+    let wg = SmartWaitGroup::collecting();
+    let waiter = wg.waiter();
+    let handles = (0..THREADS_NUMBER)
+        .map(|i| wg.spawn(move || i * 2))
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    let mut results = waiter.wait().unwrap();
+    results.sort_unstable();
+    assert_eq!(
+        results,
+        (0..THREADS_NUMBER).map(|i| i * 2).collect::<Vec<_>>()
+    );
+
+    wg.spawn(|| panic!("spawned doer")).join().unwrap();
+    wg.spawn(|| panic!("another doer")).join().unwrap();
+    match waiter.wait() {
+        Err(WaitGroupError::Poisoned { panicked, .. }) => assert_eq!(panicked, 2),
+        other => panic!("Unexpected result: {:?}", other),
+    }
+    // Only the first payload is kept
+    let payload = waiter.take_panic().unwrap();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"spawned doer"));
+    assert!(waiter.take_panic().is_none());
+    wg.clear_poison();
+
+    wg.spawn(|| panic!("resumed"));
+    let resumed =
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| waiter.wait_or_resume()))
+            .unwrap_err();
+    assert_eq!(resumed.downcast_ref::<&str>(), Some(&"resumed"));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn smart_wg_scope() {
+    let counter = AtomicI32::new(INITIAL_VALUE);
+    let wg = SmartWaitGroup::new();
+
+    wg.scope(|s| {
+        for _ in 0..THREADS_NUMBER {
+            s.spawn(|| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        s.spawn(|| panic!("scoped doer"));
+    });
+    assert_eq!(wg.counter(), 0);
+    assert_eq!(counter.load(Ordering::SeqCst), THREADS_NUMBER as i32);
+    match wg.waiter().wait() {
+        Err(WaitGroupError::Poisoned { panicked, .. }) => assert_eq!(panicked, 1),
+        other => panic!("Unexpected result: {:?}", other),
+    }
+    assert!(wg.take_panic().is_some());
+    wg.clear_poison();
+    assert!(wg.take_panic().is_none());
+    // Results of the poisoned batch are drained along with it
+    assert!(wg.waiter().wait().unwrap().is_empty());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn smart_wg_rayon() {
+    use rayon::iter::{IntoParallelIterator, ParallelIterator};
+    use rayon::ThreadPoolBuilder;
+    use wait_group::DoerParallelIterator;
+
+    let pool = ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+    let wg = SmartWaitGroup::collecting();
+    for i in 0..THREADS_NUMBER {
+        wg.spawn_in(&pool, move || i);
+    }
+    let mut results = wg.waiter().wait().unwrap();
+    results.sort_unstable();
+    assert_eq!(results, (0..THREADS_NUMBER).collect::<Vec<_>>());
+
+    let sum: usize = pool.install(|| {
+        (0..THREADS_NUMBER)
+            .into_par_iter()
+            .with_doers(&wg)
+            .map(|(i, doer)| {
+                assert!(wg.counter() > 0);
+                doer.complete(i);
+                i
+            })
+            .sum()
+    });
+    assert_eq!(sum, (0..THREADS_NUMBER).sum());
+    assert_eq!(wg.waiter().wait().unwrap().len(), THREADS_NUMBER);
+
+    wg.spawn_in(&pool, || panic!("pool doer"));
+    assert!(wg.waiter().wait().is_err());
+    assert!(wg.take_panic().is_some());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn smart_wg_rayon_wait_outside() {
+    use rayon::iter::{IntoParallelIterator, ParallelIterator};
+    use rayon::ThreadPoolBuilder;
+    use std::sync::mpsc;
+    use wait_group::DoerParallelIterator;
+
+    const ITEMS: usize = 20;
+    let pool = Arc::new(ThreadPoolBuilder::new().num_threads(1).build().unwrap());
+    let wg = SmartWaitGroup::collecting();
+    let waiter = wg.waiter();
+    let (started_sender, started_receiver) = mpsc::channel();
+
+    let worker = {
+        let pool = Arc::clone(&pool);
+        thread::spawn(move || {
+            pool.install(|| {
+                (0..ITEMS)
+                    .into_par_iter()
+                    .with_doers(&wg)
+                    .for_each(|(i, doer)| {
+                        if i == 0 {
+                            started_sender.send(()).unwrap();
+                        }
+                        thread::sleep(Duration::from_millis(5));
+                        doer.complete(i);
+                    })
+            })
+        })
+    };
+
+    // Iterator is running on the pool, the waiter outside of it sees every item
+    started_receiver.recv().unwrap();
+    assert_eq!(waiter.wait().unwrap().len(), ITEMS);
+    worker.join().unwrap();
+}
+
+#[test]
+fn manual_wg_same_generation() {
+    for _ in 0..ATTEMPTS {
+        let wg = Arc::new(ManualWaitGroup::new());
+        wg.add(1);
+        let waiters = (0..2)
+            .map(|_| {
+                let wg = Arc::clone(&wg);
+                thread::spawn(move || wg.wait().unwrap())
+            })
+            .collect::<Vec<_>>();
+        wg.done();
+        let generations = waiters
+            .into_iter()
+            .map(|waiter| waiter.join().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(generations, vec![wg.generation(); 2]);
+    }
+
+    let (waiter, doer) = SmartWaitGroup::splitted();
+    let start = waiter.generation();
+    drop(doer);
+    let (generation, results) = waiter.wait_generation().unwrap();
+    assert_eq!(generation.value(), start.value() + 1);
+    assert_eq!(results.len(), 0);
+
+    // No-ops on an empty group don't start a new generation
+    let wg = ManualWaitGroup::new();
+    let start = wg.generation();
+    wg.add(0);
+    assert!(wg.add_if_empty(0));
+    assert_eq!(wg.generation(), start);
+}
@@ -0,0 +1,54 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields};
+
+// Implements wait_group::Case for an enum of unit variants,
+// variants are indexed in order of declaration
+#[proc_macro_derive(Case)]
+pub fn derive_case(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Err(Error::new_spanned(
+                &input,
+                "Case can only be derived for enums",
+            ))
+        }
+    };
+    if data.variants.is_empty() {
+        return Err(Error::new_spanned(&input, "Case enum must have variants"));
+    }
+    let mut arms = Vec::new();
+    for (index, variant) in data.variants.iter().enumerate() {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(Error::new_spanned(
+                variant,
+                "Case variants must not have fields",
+            ));
+        }
+        let ident = &variant.ident;
+        arms.push(quote!(Self::#ident => #index));
+    }
+
+    let name = &input.ident;
+    let count = data.variants.len();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::wait_group::Case for #name #ty_generics #where_clause {
+            const COUNT: usize = #count;
+
+            fn index(self) -> usize {
+                match self {
+                    #(#arms,)*
+                }
+            }
+        }
+    })
+}